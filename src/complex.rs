@@ -0,0 +1,332 @@
+use super::error::ComponentError;
+use super::lexer::{Token, TokenType, TokenValue};
+use super::value::{dec_string, number_of, numeric_types, Value};
+use num_bigint::BigInt;
+use num_traits::Signed;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A complex number with decimal parts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn exp(self) -> Complex {
+        let r = self.re.exp();
+        Complex::new(r * self.im.cos(), r * self.im.sin())
+    }
+
+    pub fn ln(self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    pub fn log10(self) -> Complex {
+        Complex::new(self.abs().log10(), self.arg() / std::f64::consts::LN_10)
+    }
+
+    pub fn log2(self) -> Complex {
+        Complex::new(self.abs().log2(), self.arg() / std::f64::consts::LN_2)
+    }
+
+    /// The principal square root
+    pub fn sqrt(self) -> Complex {
+        let r = self.abs();
+        let re = ((r + self.re) / 2.0).sqrt();
+        let im = ((r - self.re) / 2.0).sqrt();
+
+        // The root is in the same half plane as the number
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    /// The principal arcsine, which is `-i ln(iz + sqrt(1 - z^2))`
+    pub fn asin(self) -> Complex {
+        let i = Complex::new(0.0, 1.0);
+        let one = Complex::new(1.0, 0.0);
+        -(i * (i * self + (one - self * self).sqrt()).ln())
+    }
+
+    /// The principal arccosine, which is `pi/2 - asin(z)`
+    pub fn acos(self) -> Complex {
+        Complex::new(std::f64::consts::FRAC_PI_2, 0.0) - self.asin()
+    }
+
+    /// Raise to a whole power by repeated multiplication, which keeps results like `1i 2 ^` exact
+    pub fn powi(self, exp: i64) -> Complex {
+        let mut result = Complex::new(1.0, 0.0);
+        let mut base = self;
+        let mut n = exp.unsigned_abs();
+
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+
+        if exp < 0 {
+            Complex::new(1.0, 0.0) / result
+        } else {
+            result
+        }
+    }
+
+    pub fn powc(self, exp: Complex) -> Complex {
+        if self.re == 0.0 && self.im == 0.0 {
+            return self;
+        }
+
+        (exp * self.ln()).exp()
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let denom = other.re * other.re + other.im * other.im;
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denom,
+            (self.im * other.re - self.re * other.im) / denom,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (re, im) = (dec_string(self.re), dec_string(self.im.abs()));
+
+        if self.re == 0.0 {
+            write!(f, "{}i", dec_string(self.im))
+        } else if self.im.is_sign_negative() {
+            write!(f, "{}-{}i", re, im)
+        } else {
+            write!(f, "{}+{}i", re, im)
+        }
+    }
+}
+
+/// Real numbers can be used as complex numbers with no imaginary part
+pub fn complex_of(value: &TokenValue) -> Option<Complex> {
+    match value {
+        TokenValue::ComplexValue(c) => Some(*c),
+        other => number_of(other).map(|v| Complex::new(v, 0.0)),
+    }
+}
+
+/// The types that `re`, `im`, `conj`, `abs` and `arg` work on
+pub fn scalar_types() -> Vec<TokenType> {
+    let mut types = numeric_types();
+    types.push(TokenType::ComplexLiteral);
+    types
+}
+
+fn scalar(a: &Value) -> Result<Complex, ComponentError> {
+    complex_of(&a.value).ok_or_else(|| ComponentError::WrongType {
+        values: vec![a.clone()],
+        expected: scalar_types(),
+    })
+}
+
+/// The real part of a number
+pub fn re(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    match a.value {
+        TokenValue::ComplexValue(c) => Ok(Value::dec(c.re, span).with_unit(a.unit)),
+        _ => {
+            scalar(&a)?;
+            Ok(Value { span, ..a })
+        }
+    }
+}
+
+/// The imaginary part of a number, which is 0 for real numbers
+pub fn im(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    match a.value {
+        TokenValue::ComplexValue(c) => Ok(Value::dec(c.im, span).with_unit(a.unit)),
+        _ => {
+            scalar(&a)?;
+            Ok(Value::int(0, span).with_unit(a.unit))
+        }
+    }
+}
+
+/// The complex conjugate, which leaves real numbers as they are
+pub fn conj(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    match a.value {
+        TokenValue::ComplexValue(c) => Ok(Value::complex(c.conj(), span).with_unit(a.unit)),
+        _ => {
+            scalar(&a)?;
+            Ok(Value { span, ..a })
+        }
+    }
+}
+
+/// The absolute value of a real number, or the magnitude of a complex number
+pub fn abs(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    match a.value {
+        TokenValue::IntValue(v) => {
+            Ok(Value::big_int(BigInt::from(v).abs(), span).with_unit(a.unit))
+        }
+        TokenValue::BigIntValue(ref v) => Ok(Value::big_int(v.abs(), span).with_unit(a.unit)),
+        TokenValue::RatioValue(v) if v.numer() < 0 => match v.checked_neg() {
+            Some(v) => Ok(Value::ratio(v, span).with_unit(a.unit)),
+            None => Ok(Value::dec(v.to_f64().abs(), span).with_unit(a.unit)),
+        },
+        TokenValue::RatioValue(_) => Ok(Value { span, ..a }),
+        _ => Ok(Value::dec(scalar(&a)?.abs(), span).with_unit(a.unit)),
+    }
+}
+
+/// The angle of a complex number from the positive real axis
+pub fn arg(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    Ok(Value::dec(scalar(&a)?.arg(), span))
+}
+
+/// The principal square root, which is complex for negative numbers
+///
+/// A unit needs to be a square like `m^2` to have a square root.
+pub fn sqrt(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let unit = match a.unit.sqrt() {
+        Some(unit) => unit,
+        None => {
+            return Err(ComponentError::DimensionMismatch {
+                values: vec![a],
+                expected: Some("in a square unit".to_string()),
+            })
+        }
+    };
+
+    match a.number() {
+        Some(v) if v >= 0.0 => Ok(Value::dec(v.sqrt(), span).with_unit(unit)),
+        _ => Ok(Value::complex(scalar(&a)?.sqrt(), span).with_unit(unit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_arithmetic_test() {
+        let a = Complex::new(1.0, 2.0);
+        let b = Complex::new(3.0, -1.0);
+
+        assert_eq!(a + b, Complex::new(4.0, 1.0));
+        assert_eq!(a - b, Complex::new(-2.0, 3.0));
+        assert_eq!(a * b, Complex::new(5.0, 5.0));
+        assert_eq!(a * b / b, a);
+        assert_eq!(a.conj(), Complex::new(1.0, -2.0));
+        assert_eq!(Complex::new(3.0, 4.0).abs(), 5.0);
+    }
+
+    #[test]
+    fn complex_pow_test() {
+        let i = Complex::new(0.0, 1.0);
+
+        assert_eq!(i.powi(2), Complex::new(-1.0, 0.0));
+        assert_eq!(i.powi(-1), Complex::new(0.0, -1.0));
+        assert_eq!(Complex::new(-4.0, 0.0).sqrt(), Complex::new(0.0, 2.0));
+        assert_eq!(Complex::new(3.0, -4.0).sqrt(), Complex::new(2.0, -1.0));
+
+        // e^(i pi) = -1
+        let euler =
+            Complex::new(std::f64::consts::E, 0.0).powc(Complex::new(0.0, std::f64::consts::PI));
+        assert!((euler.re + 1.0).abs() < 1e-12);
+        assert!(euler.im.abs() < 1e-12);
+    }
+
+    #[test]
+    fn complex_log_and_arc_test() {
+        let close = |a: Complex, b: Complex| (a - b).abs() < 1e-12;
+
+        assert_eq!(Complex::new(-100.0, 0.0).log10().re, 2.0);
+        assert_eq!(Complex::new(-8.0, 0.0).log2().re, 3.0);
+
+        // sin(asin(z)) = z, even outside of -1 to 1
+        let two = Complex::new(2.0, 0.0);
+        let asin = two.asin();
+        let i = Complex::new(0.0, 1.0);
+        let sin = ((i * asin).exp() - (-(i * asin)).exp()) / (i + i);
+        assert!(close(sin, two));
+        assert!(close(
+            asin,
+            Complex::new(std::f64::consts::FRAC_PI_2, -(2.0 + 3f64.sqrt()).ln())
+        ));
+        assert!(close(
+            two.acos(),
+            Complex::new(0.0, (2.0 + 3f64.sqrt()).ln())
+        ));
+    }
+
+    #[test]
+    fn complex_display_test() {
+        assert_eq!(Complex::new(3.0, 2.0).to_string(), "3+2i");
+        assert_eq!(Complex::new(3.0, -2.0).to_string(), "3-2i");
+        assert_eq!(Complex::new(0.0, 1.5).to_string(), "1.5i");
+        assert_eq!(Complex::new(2.0, 0.0).to_string(), "2+0i");
+    }
+}
@@ -1,82 +1,70 @@
-use super::lexer::Token;
+use super::error::ComponentError;
+use super::lexer::Span;
 use efcl::{bold, color, Color};
 
-pub fn invalid_type_cast_error(cast_to_type: String, one: Token, two: Token) {
-    println!(
-        "{} Invalid Type Cast [E3]",
-        color!(Color::RED, bold!("Error:").as_str()).as_str()
-    );
+fn error_header(name: &str) {
     println!(
         "{} {}",
-        one.value,
-        color!(Color::RED, bold!(&two.value).as_str())
+        color!(Color::RED, bold!("Error:").as_str()).as_str(),
+        name
     );
-
-    println!(
-        "{}{} Cannot convert <{}> to <{}>",
-        (0..one.value.len() + 1).map(|_| " ").collect::<String>(),
-        color!(Color::RED, bold!("^^^").as_str()),
-        format!("{:?}", one.token_type),
-        cast_to_type
-    )
 }
 
-pub fn stack_empty_error() {
-    println!(
-        "{} Stack Empty [E4]",
-        color!(Color::RED, bold!("Error:").as_str()).as_str()
-    );
-}
+/// Print the source line the spans are on with each span highlighted and underlined, followed by
+/// the message. Spans that are not on the same line as the first span are left out.
+fn underline(source: &[String], spans: &[Span], message: &str) {
+    let line_index = match spans.first() {
+        Some(span) => span.line,
+        None => return,
+    };
 
-pub fn wrong_type_error_first(val_one: String, val_two: String) {
-    println!(
-        "{} Wrong Type [E2]",
-        color!(Color::RED, bold!("Error:").as_str()).as_str()
-    );
-    println!(
-        "{} {}",
-        color!(Color::RED, bold!(val_one.as_str()).as_str()),
-        val_two
-    );
-    println!(
-        "{} value is not a <NumericIntLiteral> or <NumericDecLiteral>",
-        color!(Color::RED, bold!("^").as_str())
-    );
-}
+    let line = match source.get(line_index) {
+        Some(l) => l.trim_end(),
+        None => {
+            println!("{}", message);
+            return;
+        }
+    };
 
-pub fn wrong_type_error_second(val_one: String, val_two: String, val_three: String) {
-    println!(
-        "{} Wrong Type [E2]",
-        color!(Color::RED, bold!("Error:").as_str()).as_str()
-    );
-    println!(
-        "{} {} {}",
-        val_one,
-        color!(Color::RED, bold!(val_two.as_str()).as_str()),
-        val_three
-    );
-    println!(
-        "{}{}value is not a <NumericIntLiteral> or <NumericDecLiteral> because it did not parse correctly",
-        (0..val_one.len() + 1).map(|_| " ").collect::<String>(),
-        color!(Color::RED, bold!("^").as_str()),
-    );
+    let mut on_line: Vec<&Span> = spans
+        .iter()
+        .filter(|s| s.line == line_index && s.start < s.end && s.end <= line.len())
+        .collect();
+    on_line.sort_by_key(|s| s.start);
+
+    let mut highlighted = String::new();
+    let mut carets = String::new();
+    let mut last = 0;
+
+    for span in on_line {
+        // Skip spans that overlap one that has already been underlined
+        if span.start < last {
+            continue;
+        }
+
+        let before = &line[last..span.start];
+        let text = &line[span.start..span.end];
+
+        highlighted.push_str(before);
+        highlighted.push_str(&color!(Color::RED, bold!(text).as_str()));
+
+        carets.push_str(&" ".repeat(before.chars().count()));
+        carets.push_str(&color!(
+            Color::RED,
+            bold!(&"^".repeat(text.chars().count())).as_str()
+        ));
+
+        last = span.end;
+    }
+
+    highlighted.push_str(&line[last..]);
+
+    println!("{}", highlighted);
+    println!("{} {}", carets, message);
 }
 
-pub fn wrong_type_error_both(val_one: String, val_two: String, val_three: String) {
-    println!(
-        "{} Wrong Type [E2]",
-        color!(Color::RED, bold!("Error:").as_str()).as_str()
-    );
-    println!(
-        "{} {} {}",
-        color!(Color::RED, bold!(val_one.as_str()).as_str()),
-        color!(Color::RED, bold!(val_two.as_str()).as_str()),
-        val_three
-    );
-    println!(
-        "{}{}{} values are not a <NumericIntLiteral> or <NumericDecLiteral> because they did not parse correctly",
-        color!(Color::RED, bold!("^").as_str()),
-        (0..val_one.len()).map(|_| " ").collect::<String>(),
-        color!(Color::RED, bold!("^").as_str())
-    );
+/// Print an error along with the part of the source that caused it
+pub fn report_error(source: &[String], error: &ComponentError) {
+    error_header(&format!("{} [{}]", error.name(), error.code()));
+    underline(source, &error.spans(), &error.message());
 }
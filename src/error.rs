@@ -0,0 +1,260 @@
+use super::lexer::{Span, Token, TokenType};
+use super::unit::Unit;
+use super::value::Value;
+use std::fmt;
+
+/// An error that stops a line of Component code from being run
+///
+/// Each error keeps the tokens or values it is about, so their spans can point at where it
+/// happened. See `display::report_error` for printing them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentError {
+    /// [E1] Assigning a value to an existing variable of a different type
+    AssignmentTypeMismatch {
+        value: Box<Value>,
+        variable: Box<Value>,
+        variable_type: TokenType,
+    },
+
+    /// [E2] Calling an operation or function on values of the wrong type
+    WrongType {
+        values: Vec<Value>,
+        expected: Vec<TokenType>,
+    },
+
+    /// [E3] Casting a value to a type it can't be converted to
+    InvalidTypeCast {
+        value: Box<Value>,
+        cast: Box<Token>,
+        cast_to: TokenType,
+    },
+
+    /// [E4] Calling an operation without enough values on the stack
+    StackEmpty { operation: Token },
+
+    /// [E5] A symbol that has no operation
+    OperationNotImplemented { operation: Token },
+
+    /// [E6] Defining a function with a name that is already used
+    FunctionAlreadyExists { name: Token },
+
+    /// [E7] Calling an operation on vectors or matrices that are not the size it needs
+    ///
+    /// Without an `expected` size the values just need to match each other.
+    SizeMismatch {
+        values: Vec<Value>,
+        expected: Option<String>,
+    },
+
+    /// [E8] Getting a component of a vector that it doesn't have
+    IndexOutOfRange {
+        value: Box<Value>,
+        index: Box<Value>,
+    },
+
+    /// [E9] A parenthesis or square bracket without the other half of its pair
+    UnbalancedParentheses { paren: Token },
+
+    /// [E10] Inverting or solving with a matrix that has no inverse
+    SingularMatrix { value: Box<Value> },
+
+    /// [E11] Dividing by zero with `/`, `//` or `%`, or raising zero to a negative power
+    DivisionByZero { value: Box<Value> },
+
+    /// [E12] Using values with units that can't be used together, like adding meters to seconds
+    ///
+    /// Without an `expected` dimension the values just need to match each other.
+    DimensionMismatch {
+        values: Vec<Value>,
+        expected: Option<String>,
+    },
+
+    /// [E13] A unit that isn't known, or a `to` without a unit after it
+    UnknownUnit { unit: Token },
+
+    /// [E14] A number literal written wrong like `0x`, or too big to be read like `1e400`
+    InvalidLiteral { literal: Token },
+
+    /// [E15] An int result too big to work out exactly, like `2 100000000 ^`
+    NumberTooBig { values: Vec<Value> },
+}
+
+impl ComponentError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ComponentError::AssignmentTypeMismatch { .. } => "E1",
+            ComponentError::WrongType { .. } => "E2",
+            ComponentError::InvalidTypeCast { .. } => "E3",
+            ComponentError::StackEmpty { .. } => "E4",
+            ComponentError::OperationNotImplemented { .. } => "E5",
+            ComponentError::FunctionAlreadyExists { .. } => "E6",
+            ComponentError::SizeMismatch { .. } => "E7",
+            ComponentError::IndexOutOfRange { .. } => "E8",
+            ComponentError::UnbalancedParentheses { .. } => "E9",
+            ComponentError::SingularMatrix { .. } => "E10",
+            ComponentError::DivisionByZero { .. } => "E11",
+            ComponentError::DimensionMismatch { .. } => "E12",
+            ComponentError::UnknownUnit { .. } => "E13",
+            ComponentError::InvalidLiteral { .. } => "E14",
+            ComponentError::NumberTooBig { .. } => "E15",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComponentError::AssignmentTypeMismatch { .. } => "Assignment Type Mismatch",
+            ComponentError::WrongType { .. } => "Wrong Type",
+            ComponentError::InvalidTypeCast { .. } => "Invalid Type Cast",
+            ComponentError::StackEmpty { .. } => "Stack Empty",
+            ComponentError::OperationNotImplemented { .. } => "Operation Not Implemented",
+            ComponentError::FunctionAlreadyExists { .. } => "Function Already Exists",
+            ComponentError::SizeMismatch { .. } => "Size Mismatch",
+            ComponentError::IndexOutOfRange { .. } => "Index Out Of Range",
+            ComponentError::UnbalancedParentheses { .. } => "Unbalanced Parentheses",
+            ComponentError::SingularMatrix { .. } => "Singular Matrix",
+            ComponentError::DivisionByZero { .. } => "Division By Zero",
+            ComponentError::DimensionMismatch { .. } => "Dimension Mismatch",
+            ComponentError::UnknownUnit { .. } => "Unknown Unit",
+            ComponentError::InvalidLiteral { .. } => "Invalid Literal",
+            ComponentError::NumberTooBig { .. } => "Number Too Big",
+        }
+    }
+
+    /// The places in the source that caused the error
+    pub fn spans(&self) -> Vec<Span> {
+        match self {
+            ComponentError::AssignmentTypeMismatch { variable, .. } => vec![variable.span],
+            ComponentError::WrongType { values, .. } => values.iter().map(|v| v.span).collect(),
+            ComponentError::InvalidTypeCast { cast, .. } => vec![cast.span],
+            ComponentError::StackEmpty { operation } => vec![operation.span],
+            ComponentError::OperationNotImplemented { operation } => vec![operation.span],
+            ComponentError::FunctionAlreadyExists { name } => vec![name.span],
+            ComponentError::SizeMismatch { values, .. } => values.iter().map(|v| v.span).collect(),
+            ComponentError::IndexOutOfRange { index, .. } => vec![index.span],
+            ComponentError::UnbalancedParentheses { paren } => vec![paren.span],
+            ComponentError::SingularMatrix { value } => vec![value.span],
+            ComponentError::DivisionByZero { value } => vec![value.span],
+            ComponentError::DimensionMismatch { values, .. } => {
+                values.iter().map(|v| v.span).collect()
+            }
+            ComponentError::UnknownUnit { unit } => vec![unit.span],
+            ComponentError::InvalidLiteral { literal } => vec![literal.span],
+            ComponentError::NumberTooBig { values } => values.iter().map(|v| v.span).collect(),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ComponentError::AssignmentTypeMismatch {
+                value,
+                variable_type,
+                ..
+            } => format!(
+                "cannot assign value {} of type <{:?}> to a variable of type <{:?}>",
+                value, value.value_type, variable_type
+            ),
+            ComponentError::WrongType { values, expected } => {
+                let mut types: Vec<String> =
+                    expected.iter().map(|t| format!("<{:?}>", t)).collect();
+                let last = types.pop().unwrap_or_default();
+                let types = if types.is_empty() {
+                    last
+                } else {
+                    format!("{} or {}", types.join(", "), last)
+                };
+
+                if values.len() == 1 {
+                    format!("value is not a {}", types)
+                } else {
+                    format!("values are not a {}", types)
+                }
+            }
+            ComponentError::InvalidTypeCast { value, cast_to, .. } => {
+                format!("Cannot convert <{:?}> to <{:?}>", value.value_type, cast_to)
+            }
+            ComponentError::StackEmpty { .. } => {
+                "not enough values on the stack for this operation".to_string()
+            }
+            ComponentError::OperationNotImplemented { .. } => {
+                "operation is not implemented".to_string()
+            }
+            ComponentError::FunctionAlreadyExists { .. } => {
+                "function with the same name already exists".to_string()
+            }
+            ComponentError::SizeMismatch { values, expected } => {
+                let sizes: Vec<String> = values.iter().map(|v| v.shape()).collect();
+                match expected {
+                    Some(e) if values.len() == 1 => {
+                        format!("value of size {} is not {}", sizes.join(" and "), e)
+                    }
+                    Some(e) => format!("values of size {} are not {}", sizes.join(" and "), e),
+                    None => format!("sizes {} don't match", sizes.join(" and ")),
+                }
+            }
+            ComponentError::IndexOutOfRange { value, index } => format!(
+                "index {} is out of range for a vector of size {}",
+                index,
+                value.size()
+            ),
+            ComponentError::UnbalancedParentheses { paren } => {
+                let name = match paren.token_type {
+                    TokenType::LeftBracket | TokenType::RightBracket => "bracket",
+                    _ => "parenthesis",
+                };
+
+                match paren.token_type {
+                    TokenType::LeftParen | TokenType::LeftBracket => {
+                        format!("{} is never closed", name)
+                    }
+                    _ => format!("{} was never opened", name),
+                }
+            }
+            ComponentError::SingularMatrix { .. } => {
+                "matrix is singular so it has no inverse".to_string()
+            }
+            ComponentError::DivisionByZero { .. } => "cannot divide by zero".to_string(),
+            ComponentError::DimensionMismatch { values, expected } => {
+                let units: Vec<String> = values
+                    .iter()
+                    .map(|v| {
+                        if v.unit.is_none() {
+                            "without a unit".to_string()
+                        } else {
+                            format!("in {}", v.unit)
+                        }
+                    })
+                    .collect();
+                match expected {
+                    Some(e) => format!("value {} is not {}", units.join(" and "), e),
+                    None => format!("values {} don't match", units.join(" and ")),
+                }
+            }
+            ComponentError::UnknownUnit { unit } if unit.token_type == TokenType::ToKeyword => {
+                "needs a unit after it like {m}".to_string()
+            }
+            ComponentError::UnknownUnit { unit } => match Unit::parse(&unit.value) {
+                Err(name) => format!("{} is not a known unit", name),
+                Ok(_) => "unit is not known".to_string(),
+            },
+            ComponentError::InvalidLiteral { literal }
+                if literal.token_type == TokenType::MalformedLiteral =>
+            {
+                format!("{} is not written like a number", literal.value)
+            }
+            ComponentError::InvalidLiteral { literal } => {
+                format!("{} is too big to be read", literal.value)
+            }
+            ComponentError::NumberTooBig { .. } => {
+                "result is too big to work out exactly".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}]: {}", self.name(), self.code(), self.message())
+    }
+}
+
+impl std::error::Error for ComponentError {}
@@ -1,18 +1,36 @@
-use itertools::Itertools;
+use super::complex::Complex;
+use super::ratio::Ratio;
+use num_bigint::BigInt;
 
 fn is_char_symbol(ch: char) -> bool {
-    match ch {
-        '[' | ']' | '{' | '}' | '(' | ')' | '.' | ',' | ':' | ';' | '=' | '\'' | '\"' | '\\'
-        | '+' | '-' | '*' | '/' | '^' | '>' | '<' | '~' => true,
-        _ => false,
-    }
+    matches!(
+        ch,
+        '[' | ']'
+            | '{'
+            | '}'
+            | '('
+            | ')'
+            | '.'
+            | ','
+            | ':'
+            | ';'
+            | '='
+            | '\''
+            | '\"'
+            | '\\'
+            | '+'
+            | '-'
+            | '*'
+            | '/'
+            | '^'
+            | '>'
+            | '<'
+            | '~'
+    )
 }
 
 fn is_non_zero_number(ch: char) -> bool {
-    match ch {
-        '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => true,
-        _ => false,
-    }
+    matches!(ch, '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9')
 }
 
 fn is_number(ch: char) -> bool {
@@ -20,10 +38,7 @@ fn is_number(ch: char) -> bool {
 }
 
 fn is_char_whitespace(ch: char) -> bool {
-    match ch {
-        '\t' | ' ' | '\n' => true,
-        _ => false,
-    }
+    matches!(ch, '\t' | ' ' | '\n')
 }
 
 fn is_part_whitespace(string: &str) -> bool {
@@ -41,12 +56,163 @@ fn ends_token(cur: char, next: char) -> bool {
         return true;
     }
 
-    if is_char_symbol(cur) || is_char_symbol(next) {
-        return true;
+    is_char_symbol(cur) || is_char_symbol(next)
+}
+
+fn radix_of(prefix: char) -> Option<u32> {
+    match prefix {
+        'x' | 'X' => Some(16),
+        'b' | 'B' => Some(2),
+        'o' | 'O' => Some(8),
+        _ => None,
+    }
+}
+
+/// Get the length of the unit literal like `{m/s}` at the start of `chars`
+///
+/// A unit runs to the closing `}` without any whitespace in between, so the `/` in `{m/s}` doesn't
+/// end the token. A `{` that isn't closed this way is just a `{`
+fn unit_len(chars: &[char]) -> usize {
+    if chars.first() != Some(&'{') {
+        return 0;
+    }
+
+    match chars
+        .iter()
+        .position(|c| *c == '}' || is_char_whitespace(*c))
+    {
+        Some(close) if chars[close] == '}' && close > 1 => close + 1,
+        _ => 0,
+    }
+}
+
+/// Operators written with more than one symbol, which would otherwise be split into one token each
+const LONG_OPERATORS: [&str; 5] = ["//", "<=", ">=", "==", "!="];
+
+/// Get the length of an operator like `//` at the start of `chars`
+fn operator_len(chars: &[char]) -> usize {
+    LONG_OPERATORS
+        .iter()
+        .filter(|op| op.chars().eq(chars.iter().take(op.len()).copied()))
+        .map(|op| op.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Get the length of a keyword with a `-` in it like `round-to` at the start of `chars`
+///
+/// Other words with a `-` between them are still split, so `a-b` is `a`, `-` and then `b`
+fn keyword_len(chars: &[char]) -> usize {
+    let len = chars
+        .iter()
+        .take_while(|c| c.is_alphabetic() || **c == '-')
+        .count();
+    let word = String::from_iter(&chars[..len]);
+
+    if word.contains('-') && is_type(&word) != TokenType::NoType {
+        len
+    } else {
+        0
+    }
+}
+
+/// Get the length of the string literal like `"a b"` at the start of `chars`
+///
+/// A string runs to the next quote of the same kind that isn't escaped with a `\`, so it can have
+/// whitespace and symbols in it. A quote that is never closed is just a quote
+fn string_len(chars: &[char]) -> usize {
+    let quote = match chars.first() {
+        Some(q @ ('"' | '\'')) => *q,
+        _ => return 0,
+    };
+
+    let mut escaped = false;
+    for (i, c) in chars.iter().enumerate().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if *c == quote => return i + 1,
+            _ => {}
+        }
     }
 
-    if is_char_whitespace(cur) {}
-    return false;
+    0
+}
+
+/// Replace escapes like `\n` and `\"` in a string literal with the characters they stand for
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(e @ ('\\' | '"' | '\'')) => out.push(e),
+            // Anything else is kept as it was written
+            Some(e) => {
+                out.push('\\');
+                out.push(e);
+            }
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+/// Get the length of the numeric literal at the start of `chars`
+///
+/// This lets a literal like `3.14` continue past the `.` that would otherwise end the token. A
+/// `.` only belongs to the number when a digit follows it, so `3.` is still `3` and then `.`
+/// The same goes for exponents like `6.022e23` and `1.6E-19`, and for radix literals like `0xFF`
+///
+/// A `-` directly followed by a digit starts a negative literal, so `-5` is a number while `- 5`
+/// is the subtraction operator and then `5`
+fn numeric_len(chars: &[char]) -> usize {
+    if chars.first() == Some(&'-') {
+        let len = numeric_len(&chars[1..]);
+        return if len > 0 { len + 1 } else { 0 };
+    }
+
+    // Radix literals: 0xFF, 0b1010, 0o17
+    if chars.first() == Some(&'0') {
+        if let Some(radix) = chars.get(1).and_then(|c| radix_of(*c)) {
+            let digits = chars[2..].iter().take_while(|c| c.is_digit(radix)).count();
+            if digits > 0 {
+                return 2 + digits;
+            }
+        }
+    }
+
+    let mut len = chars.iter().take_while(|c| is_number(**c)).count();
+
+    if len > 0 && chars.get(len) == Some(&'.') && chars.get(len + 1).is_some_and(|c| is_number(*c))
+    {
+        len += 1;
+        len += chars[len..].iter().take_while(|c| is_number(**c)).count();
+    }
+
+    // The exponent only counts when it has digits, so `2e` is not a number
+    if len > 0 && matches!(chars.get(len), Some('e' | 'E')) {
+        let sign = usize::from(matches!(chars.get(len + 1), Some('+' | '-')));
+        let digits = chars
+            .iter()
+            .skip(len + 1 + sign)
+            .take_while(|c| is_number(**c))
+            .count();
+
+        if digits > 0 {
+            len += 1 + sign + digits;
+        }
+    }
+
+    len
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -62,6 +228,33 @@ pub enum TokenType {
 
     BoolLiteral,
 
+    // (1 2 3)
+    VectorLiteral,
+
+    // ((1 2) (3 4))
+    MatrixLiteral,
+
+    // 1/3, made with `ratio`
+    RatioLiteral,
+
+    // 3i, 2.5i
+    ImaginaryLiteral,
+
+    // 0x, 1e, 2x, which start like a number but aren't one
+    MalformedLiteral,
+
+    // 3+2i, made with `complex` or by adding to an imaginary
+    ComplexLiteral,
+
+    // {m/s}, {kg*m/s^2}
+    UnitLiteral,
+
+    // "hello", 'world'
+    StringLiteral,
+
+    // [2 *], code that is kept to run later with `call`, `if` or `ifelse`
+    QuotationLiteral,
+
     LeftBrace,
     RightBrace,
     LeftBracket,
@@ -81,6 +274,10 @@ pub enum TokenType {
 
     Greater,
     Less,
+    GreaterEqual,
+    LessEqual,
+    Equal,
+    NotEqual,
 
     Assignment,
     Semicolon,
@@ -91,6 +288,7 @@ pub enum TokenType {
     DoubleQuestion,
     At,
     Percent,
+    IntDivision,
     Bang,
     BackSlash,
 
@@ -103,6 +301,15 @@ pub enum TokenType {
 
     StackKeyword,
     VarsKeyword,
+    DupKeyword,
+    DropKeyword,
+    SwapKeyword,
+    OverKeyword,
+    RotKeyword,
+    PickKeyword,
+    RollKeyword,
+    ClearKeyword,
+    DepthKeyword,
 
     TypeNumberKeyword,
     // "int"
@@ -116,6 +323,36 @@ pub enum TokenType {
     TypeSqrtKeyword,
     TypeBoolKeyword,
     RoundKeyword,
+    RoundToKeyword,
+    FloorKeyword,
+    CeilKeyword,
+    TruncKeyword,
+    NegKeyword,
+    DotKeyword,
+    CrossKeyword,
+    NormKeyword,
+    GetKeyword,
+    TransposeKeyword,
+    DetKeyword,
+    InvKeyword,
+    SolveKeyword,
+    IdentityKeyword,
+    ReKeyword,
+    ImKeyword,
+    ConjKeyword,
+    ArgKeyword,
+    ToKeyword,
+    LenKeyword,
+    PrintKeyword,
+    AndKeyword,
+    OrKeyword,
+    XorKeyword,
+    IfKeyword,
+    IfElseKeyword,
+    CallKeyword,
+    TimesKeyword,
+    WhileKeyword,
+    ForKeyword,
 
     Function,
 
@@ -143,7 +380,46 @@ fn is_type(maybe_type: &str) -> TokenType {
         "false" => TokenType::BoolLiteral,
         "vars" => TokenType::VarsKeyword,
         "stack" => TokenType::StackKeyword,
+        "dup" => TokenType::DupKeyword,
+        "drop" => TokenType::DropKeyword,
+        "swap" => TokenType::SwapKeyword,
+        "over" => TokenType::OverKeyword,
+        "rot" => TokenType::RotKeyword,
+        "pick" => TokenType::PickKeyword,
+        "roll" => TokenType::RollKeyword,
+        "clear" => TokenType::ClearKeyword,
+        "depth" => TokenType::DepthKeyword,
         "round" => TokenType::RoundKeyword,
+        "round-to" => TokenType::RoundToKeyword,
+        "floor" => TokenType::FloorKeyword,
+        "ceil" => TokenType::CeilKeyword,
+        "trunc" => TokenType::TruncKeyword,
+        "neg" => TokenType::NegKeyword,
+        "dot" => TokenType::DotKeyword,
+        "cross" => TokenType::CrossKeyword,
+        "norm" => TokenType::NormKeyword,
+        "get" => TokenType::GetKeyword,
+        "transpose" => TokenType::TransposeKeyword,
+        "det" => TokenType::DetKeyword,
+        "inv" => TokenType::InvKeyword,
+        "solve" => TokenType::SolveKeyword,
+        "identity" => TokenType::IdentityKeyword,
+        "re" => TokenType::ReKeyword,
+        "im" => TokenType::ImKeyword,
+        "conj" => TokenType::ConjKeyword,
+        "arg" => TokenType::ArgKeyword,
+        "to" => TokenType::ToKeyword,
+        "len" => TokenType::LenKeyword,
+        "print" => TokenType::PrintKeyword,
+        "and" => TokenType::AndKeyword,
+        "or" => TokenType::OrKeyword,
+        "xor" => TokenType::XorKeyword,
+        "if" => TokenType::IfKeyword,
+        "ifelse" => TokenType::IfElseKeyword,
+        "call" => TokenType::CallKeyword,
+        "times" => TokenType::TimesKeyword,
+        "while" => TokenType::WhileKeyword,
+        "for" => TokenType::ForKeyword,
 
         // Other types
         // "literal" | "type" | "option" | "string" => true,
@@ -157,34 +433,95 @@ pub trait TokenTrait {
     fn from_chars(chars: Vec<char>) -> Self;
 }
 
-#[derive(Debug, Clone, PartialEq)]
-// TODO: Change value: String to value: TokenValue and make TokenValue an enum with many types
-// supported like int, float, string, etc.
+/// Where a token was written in the source
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    /// Index of the line the token is on
+    pub line: usize,
+    /// Character column where the token starts
+    pub column: usize,
+    /// Byte offset into the line where the token starts
+    pub start: usize,
+    /// Byte offset into the line just past the end of the token
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a span covering both `self` and `other`
+    ///
+    /// This is used for values computed from several tokens, like the result of `1 2 +`. Spans on
+    /// different lines cannot be joined, so `self` is kept as is in that case.
+    pub fn to(&self, other: Span) -> Span {
+        // An empty span has no position to join with
+        if self.start == self.end {
+            return other;
+        }
+
+        if other.start == other.end || self.line != other.line {
+            return *self;
+        }
+
+        let first = if self.start <= other.start {
+            self
+        } else {
+            &other
+        };
+
+        Span {
+            line: self.line,
+            column: first.column,
+            start: first.start,
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+// Tokens keep the text they were lexed from, the parser reads it into a `value::Value`
 pub struct Token {
     pub token_type: TokenType,
     pub value: String,
+    pub span: Span,
 }
 
+// The span is left out so that tokens compare by what they are and not where they were written
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.value == other.value
+    }
+}
+
+/// What a value holds at runtime, see `value::Value`
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenValue {
     StringValue(String),
     IntValue(i64),
+    // Only for ints too big for an i64, see `value::int_value`
+    BigIntValue(BigInt),
     DecValue(f64),
+    RatioValue(Ratio),
+    ComplexValue(Complex),
+    BoolValue(bool),
     VecValue(Vec<TokenValue>),
+    // The tokens of a quotation, which are only parsed when it is run
+    QuotationValue(Vec<Token>),
 }
 
 fn is_part_int_numeric(part: &str) -> bool {
-    let mut chars = part.chars();
+    // Negative literals like `-5` follow the same rules once the `-` is removed
+    let digits = part.strip_prefix('-').unwrap_or(part);
+    let mut chars = digits.chars();
 
     // Literal 0 case
-    let mut a = chars.clone();
-    if a.clone().count() == 1 && a.nth(0) == Some('0') {
+    if digits == "0" {
         return true;
     }
 
-    let first_char = chars
-        .nth(0)
-        .expect("Part should have more than zero characters");
-    if !(is_non_zero_number(first_char) || first_char == '-') {
+    let first_char = match chars.next() {
+        Some(c) => c,
+        None => return false,
+    };
+    if !is_non_zero_number(first_char) {
         return false;
     }
 
@@ -194,33 +531,117 @@ fn is_part_int_numeric(part: &str) -> bool {
         }
     }
 
-    return true;
+    true
 }
 
-fn is_part_alpha(part: &str) -> bool {
-    let chars = part.chars();
+fn is_part_dec_numeric(part: &str) -> bool {
+    match part.split_once('.') {
+        Some((whole, fract)) => {
+            // The whole part follows the same rules as an int, so "0.5" is fine but "00.5" is not
+            let whole_ok = !whole.is_empty() && is_part_int_numeric(whole);
+            let fract_ok = !fract.is_empty() && fract.chars().all(is_number);
+
+            whole_ok && fract_ok
+        }
+        None => false,
+    }
+}
+
+/// Read a hex, binary or octal literal like `0xFF` as its value, which can be any size
+fn parse_radix_int(part: &str) -> Option<BigInt> {
+    if let Some(positive) = part.strip_prefix('-') {
+        return parse_radix_int(positive).map(|v| -v);
+    }
+
+    let mut chars = part.chars();
+
+    if chars.next() != Some('0') {
+        return None;
+    }
+
+    let radix = radix_of(chars.next()?)?;
+    let digits = chars.as_str();
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+}
+
+/// Whether a literal is written in scientific notation like `6.022e23`, even if it is too big
+fn is_part_sci_numeric(part: &str) -> bool {
+    let (mantissa, exponent) = match part.split_once(['e', 'E']) {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    if mantissa.is_empty() || !(is_part_int_numeric(mantissa) || is_part_dec_numeric(mantissa)) {
+        return false;
+    }
+
+    let exponent_digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+    !exponent_digits.is_empty() && exponent_digits.chars().all(is_number)
+}
+
+/// Whether a literal starts like a number, which is the only thing that can start with a digit
+fn is_part_number_start(part: &str) -> bool {
+    let digits = part.strip_prefix('-').unwrap_or(part);
+    digits.starts_with(is_number)
+}
+
+/// Names start with a letter and can have numbers after it, like `log2`
+fn is_part_identifier(part: &str) -> bool {
+    let mut chars = part.chars();
+
+    match chars.next() {
+        Some(c) if c.is_alphabetic() => {}
+        _ => return false,
+    }
 
     for c in chars {
-        if !c.is_alphabetic() {
+        if !c.is_alphanumeric() {
             return false;
         }
     }
 
-    return true;
+    true
 }
 
 impl TokenTrait for Token {
     fn default() -> Self {
-        return Token {
+        Token {
             token_type: TokenType::NoType,
             value: String::new(),
-        };
+            span: Span::default(),
+        }
     }
 
     fn tokenize(tokens: String) -> Self {
         let token_str = tokens.as_str();
         let mut token = Token::default();
 
+        // The value of a string is what is inside the quotes, with the escapes replaced
+        if let Some(quote @ ('"' | '\'')) = token_str.chars().next() {
+            if token_str.len() >= 2 && token_str.ends_with(quote) {
+                token.token_type = TokenType::StringLiteral;
+                token.value = unescape(&token_str[1..token_str.len() - 1]);
+                return token;
+            }
+        }
+
+        // The value of a unit is what is inside the braces
+        if let Some(unit) = token_str
+            .strip_prefix('{')
+            .and_then(|t| t.strip_suffix('}'))
+        {
+            if !unit.is_empty() {
+                token.token_type = TokenType::UnitLiteral;
+                token.value = unit.to_string();
+                return token;
+            }
+        }
+
         if tokens.len() < 3 {
             let token_type = match token_str {
                 "{" => TokenType::LeftBrace,
@@ -237,9 +658,14 @@ impl TokenTrait for Token {
                 "-" => TokenType::Subtraction,
                 "*" => TokenType::Multiplication,
                 "/" => TokenType::Division,
+                "//" => TokenType::IntDivision,
 
                 ">" => TokenType::Greater,
                 "<" => TokenType::Less,
+                ">=" => TokenType::GreaterEqual,
+                "<=" => TokenType::LessEqual,
+                "==" => TokenType::Equal,
+                "!=" => TokenType::NotEqual,
                 "^" => TokenType::Carrot,
 
                 "=" => TokenType::Assignment,
@@ -277,6 +703,42 @@ impl TokenTrait for Token {
             return token;
         }
 
+        if is_part_dec_numeric(token_str) {
+            token.token_type = TokenType::NumericDecLiteral;
+            token.value = tokens;
+            return token;
+        }
+
+        // Other ways of writing numbers get normalized into the usual int and dec values
+        if let Some(int) = parse_radix_int(token_str) {
+            token.token_type = TokenType::NumericIntLiteral;
+            token.value = int.to_string();
+            return token;
+        }
+
+        // Scientific notation keeps its text, so a number too big for a decimal can say what is
+        // wrong when it is read
+        if is_part_sci_numeric(token_str) {
+            token.token_type = TokenType::NumericDecLiteral;
+            token.value = tokens;
+            return token;
+        }
+
+        // An `i` after a number makes it imaginary, the value is kept without the `i`
+        if let Some(number) = token_str.strip_suffix('i') {
+            if is_part_int_numeric(number) || is_part_dec_numeric(number) {
+                token.token_type = TokenType::ImaginaryLiteral;
+                token.value = number.to_string();
+                return token;
+            }
+
+            if is_part_sci_numeric(number) {
+                token.token_type = TokenType::ImaginaryLiteral;
+                token.value = number.to_string();
+                return token;
+            }
+        }
+
         // Check for type keywords like "int" and "dec"
         let tok = is_type(token_str);
         if tok != TokenType::NoType {
@@ -286,14 +748,21 @@ impl TokenTrait for Token {
         }
 
         // Check for identifiers that are not keywords
-        if is_part_alpha(token_str) {
+        if is_part_identifier(token_str) {
             token.token_type = TokenType::Identifier;
             token.value = tokens;
             return token;
         }
 
+        // Anything else that starts with a digit is a number written wrong, like `0x` or `1e`
+        if is_part_number_start(token_str) {
+            token.token_type = TokenType::MalformedLiteral;
+            token.value = tokens;
+            return token;
+        }
+
         token.value = tokens;
-        return token;
+        token
     }
 
     fn from_chars(chars: Vec<char>) -> Self {
@@ -302,90 +771,213 @@ impl TokenTrait for Token {
     }
 }
 
+fn byte_len(chars: &[char]) -> usize {
+    chars.iter().map(|c| c.len_utf8()).sum()
+}
+
 pub struct Lexer {
     line_index: usize,
     column_index: usize,
     lines: Vec<String>,
-    prev_token: Token,
+    // Every token lexed so far, so the lexer can move back and forth between them
+    tokens: Vec<Token>,
+    // Index into `tokens` of the token that `next` will return
+    token_index: usize,
+    // Give a Newline token at the end of each line instead of going straight to the next one
+    newlines: bool,
 }
 
 pub trait Lex {
     fn new(lines: Vec<String>) -> Self;
     fn next(&mut self) -> Token;
 
-    /// TODO: Get this to move back one token and return it
+    /// Move back one token and return it, so the following `next` returns the same token again
     fn prev(&mut self) -> Token;
 
-    /// TODO: Get this to show the next token without moving the column_index
+    /// Show the next token without moving past it
     fn peak(&mut self) -> Token;
-    /// TODO: Get this to show the previous token without moving the column_index
+    /// Show the token that was last returned by `next` without moving back
     fn lookback(&mut self) -> Token;
 
     fn reset_line(&mut self);
+
+    /// Choose whether a Newline token is given between lines
+    fn set_newlines(&mut self, newlines: bool);
 }
 
-impl Lex for Lexer {
-    fn new(lines: Vec<String>) -> Self {
-        Lexer {
-            line_index: 0,
-            column_index: 0,
-            lines,
-            prev_token: Token::default(),
+impl Lexer {
+    fn span_at(&self, start: usize, end: usize) -> Span {
+        Span {
+            line: self.line_index,
+            column: self.lines[self.line_index][..start].chars().count(),
+            start,
+            end,
         }
     }
 
-    fn prev(&mut self) -> Token {
-        return self.prev_token.clone();
+    /// Move past the end of a `#| ... |#` block comment, which can span several lines. An
+    /// unclosed block comment runs to the end of the program.
+    fn skip_block_comment(&mut self, mut from: usize) {
+        loop {
+            if let Some(close) = self.lines[self.line_index][from..].find("|#") {
+                self.column_index = from + close + 2;
+                return;
+            }
+
+            if self.line_index + 1 >= self.lines.len() {
+                self.column_index = self.lines[self.line_index].len();
+                return;
+            }
+
+            self.line_index += 1;
+            from = 0;
+        }
     }
 
-    fn next(&mut self) -> Token {
-        let mut buffer = Vec::<char>::new();
+    /// Read the next token from the source, regardless of what is in the token buffer
+    fn lex_token(&mut self) -> Token {
+        if self.line_index >= self.lines.len() {
+            let mut token = Token::default();
+            token.token_type = TokenType::EndToken;
+            return token;
+        }
 
-        // Note: the last character in the line will never be set to `cur`, thus will never get
-        // pushed. You could push(' ') to the line to fix this as seen below. The line is mutable
-        // anyway so there isn't much of a real disadvantage do doing this
-        self.lines[self.line_index].push(' ');
+        // Note: the last character in the line would never be followed by anything that ends the
+        // token, so a trailing space is added once to make sure the final token gets cut off
+        if !self.lines[self.line_index].ends_with(is_char_whitespace) {
+            self.lines[self.line_index].push(' ');
+        }
 
         let current_line = &self.lines[self.line_index][self.column_index..];
 
         if is_part_whitespace(current_line) {
             let mut token = Token::default();
-            token.token_type = TokenType::EndToken;
+            token.span = self.span_at(self.column_index, self.column_index);
+
+            // The whole program has been read
+            if self.line_index + 1 >= self.lines.len() {
+                token.token_type = TokenType::EndToken;
+                return token;
+            }
+
+            self.line_index += 1;
+            self.column_index = 0;
+
+            if !self.newlines {
+                return self.lex_token();
+            }
+
+            token.token_type = TokenType::Newline;
+            token.value = "\n".to_string();
             return token;
         }
 
-        // Iterate through using windows of size 2
-        // abcd -> (a, b), (b, c), (c, d)
-        for (cur, next) in current_line.chars().into_iter().tuple_windows() {
-            // Skip whitespace at the start of a new section on un-lexed line
-            if is_char_whitespace(cur) {
-                self.column_index += 1;
-                continue;
+        let chars: Vec<char> = current_line.chars().collect();
+
+        // Skip whitespace at the start of a new section on un-lexed line
+        let start = chars.iter().take_while(|c| is_char_whitespace(**c)).count();
+
+        // Comments get skipped the same way whitespace does
+        if chars[start] == '#' {
+            if chars.get(start + 1) == Some(&'|') {
+                let after_open = self.column_index + byte_len(&chars[..start + 2]);
+                self.skip_block_comment(after_open);
+            } else {
+                // A line comment runs to the end of the line
+                self.column_index = self.lines[self.line_index].len();
             }
 
-            self.column_index += 1;
-            buffer.push(cur);
-            if ends_token(cur, next) {
-                break;
+            return self.lex_token();
+        }
+
+        // Numbers, units, strings, keywords like `round-to` and operators like `//` get to read
+        // past symbols like `.` so that `3.14` stays a single token
+        let rest = &chars[start..];
+        let mut end = start
+            + numeric_len(rest)
+                .max(unit_len(rest))
+                .max(string_len(rest))
+                .max(keyword_len(rest))
+                .max(operator_len(rest));
+        if end == start {
+            end += 1;
+        }
+
+        while end < chars.len() && !ends_token(chars[end - 1], chars[end]) {
+            end += 1;
+        }
+
+        let start_byte = self.column_index + byte_len(&chars[..start]);
+        let end_byte = self.column_index + byte_len(&chars[..end]);
+        self.column_index = end_byte;
+
+        let mut token = Token::from_chars(chars[start..end].to_vec());
+        token.span = self.span_at(start_byte, end_byte);
+        token
+    }
+}
+
+impl Lex for Lexer {
+    fn new(lines: Vec<String>) -> Self {
+        Lexer {
+            line_index: 0,
+            column_index: 0,
+            lines,
+            tokens: Vec::<Token>::new(),
+            token_index: 0,
+            newlines: false,
+        }
+    }
+
+    fn set_newlines(&mut self, newlines: bool) {
+        self.newlines = newlines;
+    }
+
+    fn prev(&mut self) -> Token {
+        if self.token_index == 0 {
+            return Token::default();
+        }
+
+        self.token_index -= 1;
+        self.tokens[self.token_index].clone()
+    }
+
+    fn next(&mut self) -> Token {
+        if self.token_index == self.tokens.len() {
+            // Once the end is reached, keep giving back the same EndToken
+            if let Some(last) = self.tokens.last() {
+                if last.token_type == TokenType::EndToken {
+                    return last.clone();
+                }
             }
+
+            let token = self.lex_token();
+            self.tokens.push(token);
         }
 
-        let new_token = Token::from_chars(buffer);
-        return new_token;
+        let token = self.tokens[self.token_index].clone();
+        self.token_index += 1;
+        token
     }
 
     fn reset_line(&mut self) {
         self.column_index = 0;
+        self.tokens.clear();
+        self.token_index = 0;
     }
 
-    /// TODO: Get this to show the next token without moving the column_index
     fn peak(&mut self) -> Token {
-        Token::default()
+        let index = self.token_index;
+        let token = self.next();
+        self.token_index = index;
+        token
     }
 
-    /// TODO: Get this to show the previous token without moving the column_index
     fn lookback(&mut self) -> Token {
-        Token::default()
+        match self.token_index.checked_sub(1) {
+            Some(index) => self.tokens[index].clone(),
+            None => Token::default(),
+        }
     }
 }
 
@@ -463,6 +1055,516 @@ mod tests {
         assert_eq!(lex.next().token_type, TokenType::Division);
     }
 
+    #[test]
+    fn lexer_dec_test() {
+        let mut lex: Lexer = Lexer::new(vec!["3.14 0.5 * 2.".to_string()]);
+
+        let pi = lex.next();
+        assert_eq!(pi.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(pi.value, "3.14");
+
+        let half = lex.next();
+        assert_eq!(half.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(half.value, "0.5");
+
+        assert_eq!(lex.next().token_type, TokenType::Multiplication);
+
+        // A trailing dot is not part of the number
+        let two = lex.next();
+        assert_eq!(two.token_type, TokenType::NumericIntLiteral);
+        assert_eq!(two.value, "2");
+        assert_eq!(lex.next().token_type, TokenType::Dot);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_vector_test() {
+        let mut lex: Lexer = Lexer::new(vec!["(1 -2 3.5) (4 5 6) cross".to_string()]);
+
+        assert_eq!(lex.next().token_type, TokenType::LeftParen);
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "-2");
+        assert_eq!(lex.next().value, "3.5");
+        assert_eq!(lex.next().token_type, TokenType::RightParen);
+        assert_eq!(lex.next().token_type, TokenType::LeftParen);
+        assert_eq!(lex.next().value, "4");
+        assert_eq!(lex.next().value, "5");
+        assert_eq!(lex.next().value, "6");
+        assert_eq!(lex.next().token_type, TokenType::RightParen);
+        assert_eq!(lex.next().token_type, TokenType::CrossKeyword);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_imaginary_test() {
+        let mut lex: Lexer = Lexer::new(vec!["3i -2.5i 1e3i i 3j".to_string()]);
+
+        let three = lex.next();
+        assert_eq!(three.token_type, TokenType::ImaginaryLiteral);
+        assert_eq!(three.value, "3");
+
+        let half = lex.next();
+        assert_eq!(half.token_type, TokenType::ImaginaryLiteral);
+        assert_eq!(half.value, "-2.5");
+
+        let thousand = lex.next();
+        assert_eq!(thousand.token_type, TokenType::ImaginaryLiteral);
+        assert_eq!(thousand.value.parse::<f64>(), Ok(1000.0));
+
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+        assert_eq!(lex.next().token_type, TokenType::MalformedLiteral);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_unit_test() {
+        let mut lex: Lexer = Lexer::new(vec!["300 {m/s} {kg*m/s^2} to { }".to_string()]);
+
+        assert_eq!(lex.next().value, "300");
+
+        let speed = lex.next();
+        assert_eq!(speed.token_type, TokenType::UnitLiteral);
+        assert_eq!(speed.value, "m/s");
+
+        let force = lex.next();
+        assert_eq!(force.token_type, TokenType::UnitLiteral);
+        assert_eq!(force.value, "kg*m/s^2");
+
+        assert_eq!(lex.next().token_type, TokenType::ToKeyword);
+        assert_eq!(lex.next().token_type, TokenType::LeftBrace);
+        assert_eq!(lex.next().token_type, TokenType::RightBrace);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_string_test() {
+        let mut lex: Lexer =
+            Lexer::new(vec![r#""time: " 'it''s' "a \"b\"\n" "" "open"#.to_string()]);
+
+        let time = lex.next();
+        assert_eq!(time.token_type, TokenType::StringLiteral);
+        assert_eq!(time.value, "time: ");
+        assert_eq!(time.span.end - time.span.start, 8);
+
+        assert_eq!(lex.next().value, "it");
+        assert_eq!(lex.next().value, "s");
+
+        let escaped = lex.next();
+        assert_eq!(escaped.token_type, TokenType::StringLiteral);
+        assert_eq!(escaped.value, "a \"b\"\n");
+
+        let empty = lex.next();
+        assert_eq!(empty.token_type, TokenType::StringLiteral);
+        assert_eq!(empty.value, "");
+
+        // A quote that is never closed is only a quote
+        assert_eq!(lex.next().token_type, TokenType::DoubleQuote);
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_hyphen_keyword_test() {
+        let mut lex: Lexer = Lexer::new(vec!["2.5 1 round-to a-b".to_string()]);
+
+        lex.next();
+        lex.next();
+
+        let round_to = lex.next();
+        assert_eq!(round_to.token_type, TokenType::RoundToKeyword);
+        assert_eq!(round_to.span.end - round_to.span.start, 8);
+
+        // Only keywords keep the `-`
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+        assert_eq!(lex.next().token_type, TokenType::Subtraction);
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+    }
+
+    #[test]
+    fn lexer_operator_test() {
+        let mut lex: Lexer = Lexer::new(vec!["7 2 // 7 2 % 1 / /".to_string()]);
+
+        lex.next();
+        lex.next();
+        assert_eq!(lex.next().token_type, TokenType::IntDivision);
+        lex.next();
+        lex.next();
+        assert_eq!(lex.next().token_type, TokenType::Percent);
+        lex.next();
+
+        // Division with a space in between is two divisions
+        assert_eq!(lex.next().token_type, TokenType::Division);
+        assert_eq!(lex.next().token_type, TokenType::Division);
+
+        let mut lex: Lexer = Lexer::new(vec!["< <= > >= == != =".to_string()]);
+        let types: Vec<TokenType> = (0..7).map(|_| lex.next().token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Less,
+                TokenType::LessEqual,
+                TokenType::Greater,
+                TokenType::GreaterEqual,
+                TokenType::Equal,
+                TokenType::NotEqual,
+                TokenType::Assignment,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_identifier_test() {
+        let mut lex: Lexer = Lexer::new(vec!["log2 x1 2x".to_string()]);
+
+        let log2 = lex.next();
+        assert_eq!(log2.token_type, TokenType::Identifier);
+        assert_eq!(log2.value, "log2");
+        assert_eq!(lex.next().token_type, TokenType::Identifier);
+
+        // Names can't start with a number
+        assert_eq!(lex.next().token_type, TokenType::MalformedLiteral);
+    }
+
+    #[test]
+    fn lexer_quotation_test() {
+        let mut lex: Lexer = Lexer::new(vec!["[2 *] call 1 [neg] if".to_string()]);
+
+        assert_eq!(lex.next().token_type, TokenType::LeftBracket);
+        assert_eq!(lex.next().token_type, TokenType::NumericIntLiteral);
+        assert_eq!(lex.next().token_type, TokenType::Multiplication);
+        assert_eq!(lex.next().token_type, TokenType::RightBracket);
+        assert_eq!(lex.next().token_type, TokenType::CallKeyword);
+        assert_eq!(lex.next().token_type, TokenType::NumericIntLiteral);
+        assert_eq!(lex.next().token_type, TokenType::LeftBracket);
+        assert_eq!(lex.next().token_type, TokenType::NegKeyword);
+        assert_eq!(lex.next().token_type, TokenType::RightBracket);
+        assert_eq!(lex.next().token_type, TokenType::IfKeyword);
+
+        let mut lex: Lexer = Lexer::new(vec!["times while for".to_string()]);
+        assert_eq!(lex.next().token_type, TokenType::TimesKeyword);
+        assert_eq!(lex.next().token_type, TokenType::WhileKeyword);
+        assert_eq!(lex.next().token_type, TokenType::ForKeyword);
+    }
+
+    #[test]
+    fn unescape_test() {
+        assert_eq!(unescape(r"a\tb"), "a\tb");
+        assert_eq!(unescape(r"\\"), "\\");
+        assert_eq!(unescape(r"\'"), "'");
+        assert_eq!(unescape(r"\q"), "\\q");
+    }
+
+    #[test]
+    fn lexer_sci_and_radix_test() {
+        let mut lex: Lexer =
+            Lexer::new(vec!["6.022e23 1.6E-19 2e3 0xFF 0b1010 0o17 e".to_string()]);
+
+        let avogadro = lex.next();
+        assert_eq!(avogadro.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(avogadro.value.parse::<f64>(), Ok(6.022e23));
+
+        let charge = lex.next();
+        assert_eq!(charge.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(charge.value.parse::<f64>(), Ok(1.6e-19));
+
+        let thousands = lex.next();
+        assert_eq!(thousands.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(thousands.value, "2e3");
+
+        let hex = lex.next();
+        assert_eq!(hex.token_type, TokenType::NumericIntLiteral);
+        assert_eq!(hex.value, "255");
+
+        let bin = lex.next();
+        assert_eq!(bin.token_type, TokenType::NumericIntLiteral);
+        assert_eq!(bin.value, "10");
+
+        let oct = lex.next();
+        assert_eq!(oct.token_type, TokenType::NumericIntLiteral);
+        assert_eq!(oct.value, "15");
+
+        // Euler's number is still an identifier on its own
+        let euler = lex.next();
+        assert_eq!(euler.token_type, TokenType::Identifier);
+        assert_eq!(euler.value, "e");
+    }
+
+    #[test]
+    fn lexer_negative_test() {
+        let mut lex: Lexer = Lexer::new(vec!["-5 - 5 -0.5 -1e3 -0x10 5-".to_string()]);
+
+        let neg_five = lex.next();
+        assert_eq!(neg_five.token_type, TokenType::NumericIntLiteral);
+        assert_eq!(neg_five.value, "-5");
+
+        // With a space, `-` is subtraction
+        assert_eq!(lex.next().token_type, TokenType::Subtraction);
+        assert_eq!(lex.next().value, "5");
+
+        let neg_half = lex.next();
+        assert_eq!(neg_half.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(neg_half.value, "-0.5");
+
+        let neg_thousand = lex.next();
+        assert_eq!(neg_thousand.token_type, TokenType::NumericDecLiteral);
+        assert_eq!(neg_thousand.value, "-1e3");
+
+        let neg_hex = lex.next();
+        assert_eq!(neg_hex.token_type, TokenType::NumericIntLiteral);
+        assert_eq!(neg_hex.value, "-16");
+
+        // A trailing `-` is still subtraction
+        assert_eq!(lex.next().value, "5");
+        assert_eq!(lex.next().token_type, TokenType::Subtraction);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn parse_radix_int_test() {
+        assert_eq!(parse_radix_int("0xff"), Some(BigInt::from(255)));
+        assert_eq!(parse_radix_int("0B11"), Some(BigInt::from(3)));
+        assert_eq!(parse_radix_int("0o777"), Some(BigInt::from(511)));
+        assert_eq!(
+            parse_radix_int("0xFFFFFFFFFFFFFFFFFF"),
+            "4722366482869645213695".parse().ok()
+        );
+
+        assert_eq!(parse_radix_int("0x"), None);
+        assert_eq!(parse_radix_int("0b12"), None);
+        assert_eq!(parse_radix_int("12"), None);
+    }
+
+    #[test]
+    fn is_part_sci_numeric_test() {
+        assert!(is_part_sci_numeric("1e3"));
+        assert!(is_part_sci_numeric("2.5E+2"));
+        assert!(is_part_sci_numeric("5e-1"));
+
+        assert!(!is_part_sci_numeric("e3"));
+        assert!(!is_part_sci_numeric("1e"));
+        assert!(!is_part_sci_numeric("1e4x"));
+
+        // Too big to read, but still written as a number
+        assert!(is_part_sci_numeric("1e400"));
+        assert_eq!(
+            Token::tokenize("1e400".to_string()).token_type,
+            TokenType::NumericDecLiteral
+        );
+    }
+
+    #[test]
+    fn malformed_literal_test() {
+        for literal in ["0x", "1e", "-0b", "2x", "1e+"] {
+            let token = Token::tokenize(literal.to_string());
+            assert_eq!(token.token_type, TokenType::MalformedLiteral);
+            assert_eq!(token.value, literal);
+        }
+
+        // Names can still have numbers after the first letter
+        assert_eq!(
+            Token::tokenize("log2".to_string()).token_type,
+            TokenType::Identifier
+        );
+    }
+
+    #[test]
+    fn lexer_multi_line_test() {
+        let lines = vec!["1 2".to_string(), "".to_string(), "+".to_string()];
+
+        let mut lex: Lexer = Lexer::new(lines.clone());
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2");
+
+        let plus = lex.next();
+        assert_eq!(plus.value, "+");
+        assert_eq!(plus.span.line, 2);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+
+        // With newlines on, each line ends with a Newline token
+        let mut lex: Lexer = Lexer::new(lines);
+        lex.set_newlines(true);
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().token_type, TokenType::Newline);
+        assert_eq!(lex.next().token_type, TokenType::Newline);
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_comment_test() {
+        let mut lex: Lexer = Lexer::new(vec!["1 2 + # add them #| not a block".to_string()]);
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+
+        let mut lex: Lexer = Lexer::new(vec!["1 #| one |# 2 #|two|#+".to_string()]);
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+
+        // Only a `#` at the start of a token starts a comment
+        let mut lex: Lexer = Lexer::new(vec!["a#b".to_string()]);
+        assert_eq!(lex.next().value, "a#b");
+    }
+
+    #[test]
+    fn lexer_multi_line_comment_test() {
+        let lines = vec![
+            "# speed".to_string(),
+            "1 #| a block".to_string(),
+            "still in the block".to_string(),
+            "done |# 2".to_string(),
+            "+ #| never closed".to_string(),
+            "3".to_string(),
+        ];
+
+        let mut lex: Lexer = Lexer::new(lines);
+        lex.set_newlines(true);
+
+        assert_eq!(lex.next().token_type, TokenType::Newline);
+        assert_eq!(lex.next().value, "1");
+
+        // The lines inside of the block comment don't give Newline tokens
+        let two = lex.next();
+        assert_eq!(two.value, "2");
+        assert_eq!(two.span.line, 3);
+
+        assert_eq!(lex.next().token_type, TokenType::Newline);
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_peak_test() {
+        let mut lex: Lexer = Lexer::new(vec!["1 2 +".to_string()]);
+
+        // Peaking does not move forward, no matter how many times it's called
+        assert_eq!(lex.peak().value, "1");
+        assert_eq!(lex.peak().value, "1");
+        assert_eq!(lex.next().value, "1");
+
+        assert_eq!(lex.peak().value, "2");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().value, "+");
+
+        assert_eq!(lex.peak().token_type, TokenType::EndToken);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+        assert_eq!(lex.next().token_type, TokenType::EndToken);
+    }
+
+    #[test]
+    fn lexer_lookback_test() {
+        let mut lex: Lexer = Lexer::new(vec!["1 2 +".to_string()]);
+
+        // Nothing has been read yet
+        assert_eq!(lex.lookback().token_type, TokenType::NoType);
+
+        lex.next();
+        assert_eq!(lex.lookback().value, "1");
+        assert_eq!(lex.lookback().value, "1");
+
+        lex.next();
+        assert_eq!(lex.lookback().value, "2");
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.lookback().value, "+");
+    }
+
+    #[test]
+    fn lexer_prev_test() {
+        let mut lex: Lexer = Lexer::new(vec!["1 2 +".to_string()]);
+
+        // Can't go back before the first token
+        assert_eq!(lex.prev().token_type, TokenType::NoType);
+
+        lex.next();
+        lex.next();
+        assert_eq!(lex.prev().value, "2");
+        assert_eq!(lex.prev().value, "1");
+        assert_eq!(lex.lookback().token_type, TokenType::NoType);
+
+        // Moving back means the same tokens come out again
+        assert_eq!(lex.next().value, "1");
+        assert_eq!(lex.next().value, "2");
+        assert_eq!(lex.next().value, "+");
+        assert_eq!(lex.prev().value, "+");
+        assert_eq!(lex.next().value, "+");
+    }
+
+    #[test]
+    fn lexer_span_test() {
+        let mut lex: Lexer = Lexer::new(vec!["  12 3.5   foo".to_string()]);
+
+        assert_eq!(
+            lex.next().span,
+            Span {
+                line: 0,
+                column: 2,
+                start: 2,
+                end: 4,
+            }
+        );
+        assert_eq!(
+            lex.next().span,
+            Span {
+                line: 0,
+                column: 5,
+                start: 5,
+                end: 8,
+            }
+        );
+        assert_eq!(
+            lex.next().span,
+            Span {
+                line: 0,
+                column: 11,
+                start: 11,
+                end: 14,
+            }
+        );
+
+        // Columns count characters while start and end count bytes
+        let mut lex: Lexer = Lexer::new(vec!["π 1".to_string()]);
+        lex.next();
+        assert_eq!(
+            lex.next().span,
+            Span {
+                line: 0,
+                column: 2,
+                start: 3,
+                end: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn span_to_test() {
+        let one = Span {
+            line: 0,
+            column: 0,
+            start: 0,
+            end: 1,
+        };
+        let plus = Span {
+            line: 0,
+            column: 4,
+            start: 4,
+            end: 5,
+        };
+
+        let both = Span {
+            line: 0,
+            column: 0,
+            start: 0,
+            end: 5,
+        };
+        assert_eq!(one.to(plus), both);
+        assert_eq!(plus.to(one), both);
+        assert_eq!(Span::default().to(plus), plus);
+    }
+
     #[test]
     fn is_char_symbol_test() {
         assert!(is_char_symbol('+'));
@@ -477,6 +1579,37 @@ mod tests {
     fn is_part_int_numeric_test() {
         assert!(is_part_int_numeric("1"));
         assert!(is_part_int_numeric("0"));
+        assert!(is_part_int_numeric("-5"));
+        assert!(is_part_int_numeric("-0"));
+
+        assert!(!is_part_int_numeric("-"));
+        assert!(!is_part_int_numeric("-05"));
+        assert!(!is_part_int_numeric("--5"));
+    }
+
+    #[test]
+    fn is_part_dec_numeric_test() {
+        assert!(is_part_dec_numeric("3.14"));
+        assert!(is_part_dec_numeric("0.5"));
+        assert!(is_part_dec_numeric("10.0"));
+
+        assert!(!is_part_dec_numeric("1"));
+        assert!(!is_part_dec_numeric("00.5"));
+        assert!(!is_part_dec_numeric(".5"));
+        assert!(!is_part_dec_numeric("5."));
+        assert!(!is_part_dec_numeric("1.2.3"));
+    }
+
+    #[test]
+    fn numeric_len_test() {
+        let chars: Vec<char> = "3.14 ".chars().collect();
+        assert_eq!(numeric_len(&chars), 4);
+
+        let chars: Vec<char> = "3. ".chars().collect();
+        assert_eq!(numeric_len(&chars), 1);
+
+        let chars: Vec<char> = "abc".chars().collect();
+        assert_eq!(numeric_len(&chars), 0);
     }
 
     #[test]
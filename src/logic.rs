@@ -0,0 +1,167 @@
+use super::complex::complex_of;
+use super::error::ComponentError;
+use super::lexer::{Token, TokenType, TokenValue};
+use super::value::{
+    big_int_of, combine_units, is_numeric, number_of, numeric_types, operand_types, ratio_of, Value,
+};
+use std::cmp::Ordering;
+
+/// Booleans are compared like the numbers 1 and 0
+fn as_number(value: &TokenValue) -> TokenValue {
+    match value {
+        TokenValue::BoolValue(b) => TokenValue::IntValue(*b as i64),
+        other => other.clone(),
+    }
+}
+
+/// Whether two numbers, vectors or matrices are the same, with ints and ratios compared exactly
+fn equal(a: &TokenValue, b: &TokenValue) -> bool {
+    match (a, b) {
+        (TokenValue::VecValue(x), TokenValue::VecValue(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(m, n)| equal(m, n))
+        }
+        (TokenValue::VecValue(_), _) | (_, TokenValue::VecValue(_)) => false,
+        _ => {
+            if let (Some(x), Some(y)) = (big_int_of(a), big_int_of(b)) {
+                return x == y;
+            }
+            if let (Some(x), Some(y)) = (ratio_of(a), ratio_of(b)) {
+                return x == y;
+            }
+            complex_of(a).is_some() && complex_of(a) == complex_of(b)
+        }
+    }
+}
+
+/// The order of two real numbers, or `None` if either is not a number
+fn order(a: &TokenValue, b: &TokenValue) -> Option<Ordering> {
+    if let (Some(x), Some(y)) = (big_int_of(a), big_int_of(b)) {
+        return Some(x.cmp(&y));
+    }
+    if let (Some(x), Some(y)) = (ratio_of(a), ratio_of(b)) {
+        let left = x.numer() as i128 * y.denom() as i128;
+        let right = y.numer() as i128 * x.denom() as i128;
+        return Some(left.cmp(&right));
+    }
+
+    number_of(a)?.partial_cmp(&number_of(b)?)
+}
+
+/// The types that can be compared with an operator
+fn comparable_types(op: &TokenType) -> Vec<TokenType> {
+    let mut types = match op {
+        TokenType::Equal | TokenType::NotEqual => operand_types(),
+        _ => numeric_types(),
+    };
+    types.push(TokenType::BoolLiteral);
+    types.push(TokenType::StringLiteral);
+    types
+}
+
+/// Compare two values with one of `< > <= >= == !=`, giving a boolean
+///
+/// Numbers with units are compared in the same unit, so `1 {km} 500 {m} >` is true. Strings are
+/// compared in alphabetical order, and vectors and matrices can only be checked for being equal.
+pub fn compare(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let op = &operation.token_type;
+
+    let is_string = |v: &Value| v.value_type == TokenType::StringLiteral;
+    let is_number = |v: &Value| is_numeric(v) || v.value_type == TokenType::BoolLiteral;
+
+    let ordering = if is_string(&a) && is_string(&b) {
+        Some(a.value.to_string().cmp(&b.value.to_string()))
+    } else {
+        // Strings only compare with strings, and numbers with numbers
+        let wrong: Vec<Value> = if is_string(&a) || is_string(&b) {
+            [a.clone(), b.clone()]
+                .into_iter()
+                .filter(|v| !is_string(v))
+                .collect()
+        } else {
+            [a.clone(), b.clone()]
+                .into_iter()
+                .filter(|v| !is_number(v))
+                .collect()
+        };
+
+        if !wrong.is_empty() {
+            return Err(ComponentError::WrongType {
+                values: wrong,
+                expected: comparable_types(op),
+            });
+        }
+
+        let (b, _) = combine_units(&a, b, &TokenType::Subtraction)?;
+        let (x, y) = (as_number(&a.value), as_number(&b.value));
+
+        if matches!(op, TokenType::Equal | TokenType::NotEqual) {
+            let same = equal(&x, &y);
+            return Ok(Value::boolean(same == (*op == TokenType::Equal), span));
+        }
+
+        // Only real numbers have an order
+        let wrong: Vec<Value> = [a, b]
+            .into_iter()
+            .filter(|v| number_of(&as_number(&v.value)).is_none())
+            .collect();
+        if !wrong.is_empty() {
+            return Err(ComponentError::WrongType {
+                values: wrong,
+                expected: comparable_types(op),
+            });
+        }
+
+        order(&x, &y)
+    };
+
+    // Nothing is in order with `NaN`, so only `!=` is true
+    let out = match ordering {
+        Some(ordering) => match op {
+            TokenType::Less => ordering == Ordering::Less,
+            TokenType::Greater => ordering == Ordering::Greater,
+            TokenType::LessEqual => ordering != Ordering::Greater,
+            TokenType::GreaterEqual => ordering != Ordering::Less,
+            TokenType::Equal => ordering == Ordering::Equal,
+            _ => ordering != Ordering::Equal,
+        },
+        None => *op == TokenType::NotEqual,
+    };
+
+    Ok(Value::boolean(out, span))
+}
+
+/// Whether a value counts as true, which is any number that isn't 0 like with `bool`
+pub fn truth(a: &Value) -> Result<bool, ComponentError> {
+    match a.as_f64() {
+        Some(v) => Ok(v != 0.0),
+        None => {
+            let mut expected = vec![TokenType::BoolLiteral];
+            expected.extend(numeric_types());
+            Err(ComponentError::WrongType {
+                values: vec![a.clone()],
+                expected,
+            })
+        }
+    }
+}
+
+/// Combine two values with `and`, `or` or `xor`, giving a boolean
+pub fn logic(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let (x, y) = (truth(&a)?, truth(&b)?);
+
+    let out = match operation.token_type {
+        TokenType::AndKeyword => x && y,
+        TokenType::OrKeyword => x || y,
+        _ => x != y,
+    };
+
+    Ok(Value::boolean(out, span))
+}
+
+/// The opposite of a value as a boolean, so `0 !` is true and `2 !` is false
+pub fn not(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    Ok(Value::boolean(!truth(&a)?, span))
+}
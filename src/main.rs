@@ -1,14 +1,25 @@
 use crate::lexer::{Lex, Lexer, Token, TokenType};
+use display::report_error;
 use efcl::{bold, color, Color};
-use parse::{create_parser, AssemblyArchitecture, Parser};
+use parse::{create_parser, split_statements, AssemblyArchitecture, Parser};
+use round::RoundMode;
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
 use std::io::{stdin, stdout, Write};
 use structopt::StructOpt;
 
+pub mod complex;
 pub mod display;
+pub mod error;
 pub mod lexer;
+pub mod logic;
+pub mod math;
+pub mod matrix;
 pub mod parse;
+pub mod ratio;
+pub mod round;
+pub mod unit;
+pub mod value;
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -24,68 +35,85 @@ struct Opt {
     #[structopt(short, long)]
     asm: Option<String>,
 
+    /// Use `even` to round halves to the nearest even int, or `away` to round them away from zero
+    #[structopt(short, long, possible_values = &["even", "away"])]
+    round: Option<String>,
+
     /// Specify an input Component file to be run
     #[structopt(short, long)]
     filename: Option<String>,
 }
 
-fn run_file(filename: String, verbose: bool) {
+/// Lex every token from the lexer until the EndToken
+fn lex_tokens(lex: &mut Lexer, verbose: bool) -> Vec<Token> {
+    let mut tokens = Vec::<Token>::new();
+
+    loop {
+        let a = lex.next();
+
+        if a.token_type == TokenType::EndToken {
+            break;
+        }
+
+        if verbose {
+            println!(
+                "{}",
+                color!(Color::BLACK, format!("Lexing Token: {:?}", a).as_str())
+            );
+        }
+        tokens.push(a);
+    }
+
+    if verbose {
+        println!("{}", color!(Color::BLACK, "End of lexing"));
+    }
+
+    tokens
+}
+
+fn run_file(filename: String, verbose: bool, round_mode: RoundMode) {
     let mut p = create_parser(verbose);
+    p.set_round_mode(round_mode);
 
     let file_res = File::open(filename);
-    match file_res {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-
-            for line in reader.lines() {
-                match line {
-                    Ok(l) => {
-                        println!("{}{}", color!(Color::GREEN, bold!("\n> ").as_str()), l);
-
-                        let mut lex = Lexer::new(vec![l]);
-
-                        let mut tokens = Vec::<Token>::new();
-
-                        loop {
-                            let a = lex.next();
-
-                            if a.token_type == TokenType::EndToken {
-                                break;
-                            }
-
-                            if verbose {
-                                println!(
-                                    "{}",
-                                    color!(Color::BLACK, format!("Lexing Token: {:?}", a).as_str())
-                                );
-                            }
-                            tokens.push(a);
-                        }
-
-                        if verbose {
-                            println!("{}", color!(Color::BLACK, "End of lexing"));
-                        }
-
-                        let out = p.parse(tokens);
-
-                        if out.token_type != TokenType::NoType {
-                            println!(
-                                "{} {}",
-                                color!(Color::GREEN, bold!("->").as_str()),
-                                color!(Color::BLUE, bold!(&out.value).as_str())
-                            );
-                        }
+    if let Ok(file) = file_res {
+        let reader = BufReader::new(file);
+        let lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
+
+        // The whole file is lexed at once, so things like functions can span several lines
+        let mut lex = Lexer::new(lines.clone());
+        lex.set_newlines(true);
+
+        let tokens = lex_tokens(&mut lex, verbose);
+
+        // Each statement is still run and printed on its own, like typing it into the prompt
+        for statement in split_statements(&tokens) {
+            let first = statement.first().map_or(0, |t| t.span.line);
+            let last = statement.last().map_or(0, |t| t.span.line);
+            for (i, l) in lines[first..=last].iter().enumerate() {
+                let prompt = if i == 0 { "\n> " } else { "  " };
+                println!("{}{}", color!(Color::GREEN, bold!(prompt).as_str()), l);
+            }
+
+            match p.parse(statement) {
+                Ok(out) => {
+                    if out.value_type != TokenType::NoType {
+                        println!(
+                            "{} {}",
+                            color!(Color::GREEN, bold!("->").as_str()),
+                            color!(Color::BLUE, bold!(&out.to_string()).as_str())
+                        );
                     }
-                    Err(_) => {}
                 }
+                Err(e) => report_error(&lines, &e),
             }
         }
-        Err(_) => {}
     }
 }
 
-fn interactive(verbose: bool, asm: Option<AssemblyArchitecture>) {
+fn interactive(verbose: bool, asm: Option<AssemblyArchitecture>, round_mode: RoundMode) {
     let mut p = create_parser(verbose);
+    p.set_round_mode(round_mode);
 
     if let Some(a) = asm {
         p.set_asm_arch(a);
@@ -114,30 +142,10 @@ fn interactive(verbose: bool, asm: Option<AssemblyArchitecture>) {
 
         let _ = stdin().read_line(&mut input);
 
-        let mut lex = Lexer::new(vec![input]);
-
-        let mut tokens = Vec::<Token>::new();
-
-        loop {
-            let a = lex.next();
-
-            if a.token_type == TokenType::EndToken {
-                break;
-            }
-
-            if verbose {
-                println!(
-                    "{}",
-                    color!(Color::BLACK, format!("Lexing Token: {:?}", a).as_str())
-                );
-            }
-            tokens.push(a);
-        }
-
-        if verbose {
-            println!("{}", color!(Color::BLACK, "End of lexing"));
-        }
+        let lines = vec![input];
+        let mut lex = Lexer::new(lines.clone());
 
+        let tokens = lex_tokens(&mut lex, verbose);
         let out = p.parse(tokens);
 
         if asm.is_some() {
@@ -146,12 +154,17 @@ fn interactive(verbose: bool, asm: Option<AssemblyArchitecture>) {
             }
         }
 
-        if out.token_type != TokenType::NoType {
-            println!(
-                "{} {}",
-                color!(Color::GREEN, bold!("->").as_str()),
-                color!(Color::BLUE, bold!(&out.value).as_str())
-            );
+        match out {
+            Ok(out) => {
+                if out.value_type != TokenType::NoType {
+                    println!(
+                        "{} {}",
+                        color!(Color::GREEN, bold!("->").as_str()),
+                        color!(Color::BLUE, bold!(&out.to_string()).as_str())
+                    );
+                }
+            }
+            Err(e) => report_error(&lines, &e),
         }
     }
 }
@@ -159,8 +172,13 @@ fn interactive(verbose: bool, asm: Option<AssemblyArchitecture>) {
 fn main() {
     let opt = Opt::from_args();
 
+    let mut round_mode = RoundMode::HalfAwayFromZero;
+    if opt.round == Some("even".to_string()) {
+        round_mode = RoundMode::HalfEven;
+    }
+
     if let Some(filename) = opt.filename {
-        run_file(filename, opt.verbose);
+        run_file(filename, opt.verbose, round_mode);
     } else {
         let mut asm = None;
         if opt.asm == Some("x86".to_string()) || opt.asm == Some("x86-64".to_string()) {
@@ -169,6 +187,6 @@ fn main() {
             asm = Some(AssemblyArchitecture::RISCV);
         }
 
-        interactive(opt.verbose, asm);
+        interactive(opt.verbose, asm, round_mode);
     }
 }
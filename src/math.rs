@@ -0,0 +1,221 @@
+use super::complex::{complex_of, Complex};
+use super::error::ComponentError;
+use super::lexer::{Token, TokenType, TokenValue};
+use super::value::{arithmetic, big_int_of, combine_units, numeric_types, Value, MAX_INT_BITS};
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+/// The value as a decimal, for functions that only work on real numbers
+fn number(a: &Value) -> Result<f64, ComponentError> {
+    a.number().ok_or_else(|| ComponentError::WrongType {
+        values: vec![a.clone()],
+        expected: numeric_types(),
+    })
+}
+
+/// The value as a decimal, for functions like `sin` that don't make sense with a unit
+fn dimensionless(a: &Value) -> Result<f64, ComponentError> {
+    if !a.unit.is_none() {
+        return Err(ComponentError::DimensionMismatch {
+            values: vec![a.clone()],
+            expected: Some("dimensionless".to_string()),
+        });
+    }
+
+    number(a)
+}
+
+/// The value as an int, for functions like `gcd` that only work on whole numbers
+fn whole(a: &Value) -> Result<BigInt, ComponentError> {
+    if !a.unit.is_none() {
+        return Err(ComponentError::DimensionMismatch {
+            values: vec![a.clone()],
+            expected: Some("dimensionless".to_string()),
+        });
+    }
+
+    big_int_of(&a.value).ok_or_else(|| ComponentError::WrongType {
+        values: vec![a.clone()],
+        expected: vec![TokenType::NumericIntLiteral],
+    })
+}
+
+fn real_function(a: Value, operation: &Token, f: fn(f64) -> f64) -> Result<Value, ComponentError> {
+    let v = dimensionless(&a)?;
+    Ok(Value::dec(f(v), a.span.to(operation.span)))
+}
+
+/// A function that is complex for complex numbers and for real numbers outside of `domain`, like
+/// `ln` of a negative number, instead of giving back `NaN`
+fn complex_outside(
+    a: Value,
+    operation: &Token,
+    domain: fn(f64) -> bool,
+    real: fn(f64) -> f64,
+    complex: fn(Complex) -> Complex,
+) -> Result<Value, ComponentError> {
+    match complex_of(&a.value) {
+        Some(c) if a.unit.is_none() && (c.im != 0.0 || !domain(c.re)) => {
+            Ok(Value::complex(complex(c), a.span.to(operation.span)))
+        }
+        _ => real_function(a, operation, real),
+    }
+}
+
+pub fn sin(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::sin)
+}
+
+pub fn cos(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::cos)
+}
+
+pub fn tan(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::tan)
+}
+
+pub fn asin(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    complex_outside(a, operation, |v| v.abs() <= 1.0, f64::asin, Complex::asin)
+}
+
+pub fn acos(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    complex_outside(a, operation, |v| v.abs() <= 1.0, f64::acos, Complex::acos)
+}
+
+pub fn atan(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::atan)
+}
+
+pub fn sinh(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::sinh)
+}
+
+pub fn cosh(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::cosh)
+}
+
+pub fn tanh(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    real_function(a, operation, f64::tanh)
+}
+
+pub fn log(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    complex_outside(a, operation, |v| v >= 0.0, f64::log10, Complex::log10)
+}
+
+pub fn log2(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    complex_outside(a, operation, |v| v >= 0.0, f64::log2, Complex::log2)
+}
+
+/// The natural log, which is complex for negative and complex numbers like `sqrt` is
+pub fn ln(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    complex_outside(a, operation, |v| v >= 0.0, f64::ln, Complex::ln)
+}
+
+pub fn exp(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    match a.value {
+        TokenValue::ComplexValue(c) if a.unit.is_none() => {
+            Ok(Value::complex(c.exp(), a.span.to(operation.span)))
+        }
+        _ => real_function(a, operation, f64::exp),
+    }
+}
+
+/// The angle of the point `(x, y)` from the positive x axis, written `y x atan2`
+pub fn atan2(y: Value, x: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = y.span.to(operation.span);
+    let (x, _) = combine_units(&y, x, &TokenType::Subtraction)?;
+    Ok(Value::dec(number(&y)?.atan2(number(&x)?), span))
+}
+
+/// The smaller or larger of two numbers, picked with `pick_first` and given back as it was
+fn pick(
+    a: Value,
+    b: Value,
+    operation: &Token,
+    pick_first: fn(f64, f64) -> bool,
+) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let (b, _) = combine_units(&a, b, &TokenType::Subtraction)?;
+
+    let out = if pick_first(number(&a)?, number(&b)?) {
+        a
+    } else {
+        Value { unit: a.unit, ..b }
+    };
+
+    Ok(Value { span, ..out })
+}
+
+pub fn min(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    pick(a, b, operation, |x, y| x <= y)
+}
+
+pub fn max(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    pick(a, b, operation, |x, y| x >= y)
+}
+
+/// The remainder of `a / b` with the same sign as `b`, which is the same as `%`
+pub fn modulo(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let percent = Token {
+        token_type: TokenType::Percent,
+        ..operation.clone()
+    };
+    arithmetic(a, b, &percent)
+}
+
+fn big_gcd(mut a: BigInt, mut b: BigInt) -> BigInt {
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+
+    a.abs()
+}
+
+/// The greatest common divisor of two ints
+pub fn gcd(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let out = big_gcd(whole(&a)?, whole(&b)?);
+    Ok(Value::big_int(out, span))
+}
+
+/// The least common multiple of two ints, which is 0 if either is 0
+pub fn lcm(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let (x, y) = (whole(&a)?, whole(&b)?);
+
+    let out = if x.is_zero() || y.is_zero() {
+        BigInt::zero()
+    } else {
+        (&x / big_gcd(x.clone(), y.clone()) * y).abs()
+    };
+
+    Ok(Value::big_int(out, span))
+}
+
+/// The factorial of an int that isn't negative
+///
+/// Factorials too big to work out exactly are an error, like other ints that get too big.
+pub fn fact(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    let n = whole(&a)?;
+    if n.is_negative() {
+        return Err(ComponentError::WrongType {
+            values: vec![a],
+            expected: vec![TokenType::NumericIntLiteral],
+        });
+    }
+
+    // Stirling's approximation of the number of bits in n!, so it isn't worked out to find out
+    let n = n.to_u64().unwrap_or(u64::MAX);
+    let x = n as f64;
+    let bits = (x * x.ln() - x + (std::f64::consts::TAU * x).ln() / 2.0) / std::f64::consts::LN_2;
+    if n > 1 && bits > MAX_INT_BITS as f64 {
+        return Err(ComponentError::NumberTooBig { values: vec![a] });
+    }
+
+    let out = (2..=n).fold(BigInt::one(), |total, k| total * k);
+    Ok(Value::big_int(out, span))
+}
@@ -0,0 +1,431 @@
+use super::error::ComponentError;
+use super::lexer::{Token, TokenType, TokenValue};
+use super::ratio::Ratio;
+use super::unit::too_big;
+use super::value::{number_of, ratio_of, sum_of_products, Value};
+
+/// A pivot this much smaller than the largest entry of the matrix is treated as zero
+const PIVOT_EPSILON: f64 = 1e-12;
+
+/// `identity` can't make a matrix bigger than this, which already has a million components
+const MAX_IDENTITY_SIZE: i64 = 1000;
+
+/// The rows of a vector of vectors
+fn rows_of(value: &TokenValue) -> Vec<Vec<TokenValue>> {
+    match value {
+        TokenValue::VecValue(rows) => rows
+            .iter()
+            .map(|row| match row {
+                TokenValue::VecValue(r) => r.clone(),
+                other => vec![other.clone()],
+            })
+            .collect(),
+        other => vec![vec![other.clone()]],
+    }
+}
+
+fn matrix_types() -> Vec<TokenType> {
+    vec![TokenType::MatrixLiteral]
+}
+
+/// Check that the value is a matrix, giving back its rows
+fn matrix(value: &Value) -> Result<Vec<Vec<TokenValue>>, ComponentError> {
+    if value.value_type != TokenType::MatrixLiteral {
+        return Err(ComponentError::WrongType {
+            values: vec![value.clone()],
+            expected: matrix_types(),
+        });
+    }
+
+    Ok(rows_of(&value.value))
+}
+
+/// Check that the value is a matrix with as many rows as columns, giving back its rows
+fn square(value: &Value) -> Result<Vec<Vec<TokenValue>>, ComponentError> {
+    let rows = matrix(value)?;
+
+    if rows.iter().any(|row| row.len() != rows.len()) {
+        return Err(ComponentError::SizeMismatch {
+            values: vec![value.clone()],
+            expected: Some("square".to_string()),
+        });
+    }
+
+    Ok(rows)
+}
+
+fn transposed(rows: &[Vec<TokenValue>]) -> Vec<Vec<TokenValue>> {
+    let columns = rows.first().map_or(0, |row| row.len());
+
+    (0..columns)
+        .map(|j| rows.iter().map(|row| row[j].clone()).collect())
+        .collect()
+}
+
+fn multiply(a: &[Vec<TokenValue>], b: &[Vec<TokenValue>]) -> Option<Vec<Vec<TokenValue>>> {
+    let columns = transposed(b);
+
+    if a.iter().any(|row| row.len() != b.len()) {
+        return None;
+    }
+
+    Some(
+        a.iter()
+            .map(|row| columns.iter().map(|c| sum_of_products(row, c)).collect())
+            .collect(),
+    )
+}
+
+/// Multiply two matrices, a matrix by a vector, or a vector by a matrix
+///
+/// A vector on the right is used as a column and a vector on the left is used as a row, and the
+/// result of either is a vector again. Gives back `None` when the sizes don't line up.
+pub fn product(a: &Value, b: &Value) -> Option<TokenValue> {
+    let left = match a.value_type {
+        TokenType::VectorLiteral => vec![a.components()?.clone()],
+        _ => rows_of(&a.value),
+    };
+    let right = match b.value_type {
+        TokenType::VectorLiteral => transposed(&[b.components()?.clone()]),
+        _ => rows_of(&b.value),
+    };
+
+    let rows = multiply(&left, &right)?;
+
+    Some(match (&a.value_type, &b.value_type) {
+        (TokenType::VectorLiteral, _) | (_, TokenType::VectorLiteral) => {
+            TokenValue::VecValue(rows.into_iter().flatten().collect())
+        }
+        _ => TokenValue::VecValue(rows.into_iter().map(TokenValue::VecValue).collect()),
+    })
+}
+
+fn to_f64(rows: &[Vec<TokenValue>]) -> Vec<Vec<f64>> {
+    rows.iter()
+        .map(|row| row.iter().filter_map(number_of).collect())
+        .collect()
+}
+
+/// The rows as exact ratios, or `None` if any entry is a decimal or too big for a ratio
+fn to_ratio(rows: &[Vec<TokenValue>]) -> Option<Vec<Vec<Ratio>>> {
+    rows.iter()
+        .map(|row| row.iter().map(ratio_of).collect())
+        .collect()
+}
+
+/// The largest entry of a matrix ignoring its sign, which pivots are compared against
+fn largest(m: &[Vec<f64>]) -> f64 {
+    m.iter().flatten().fold(0.0, |top, v| top.max(v.abs()))
+}
+
+fn to_i64(rows: &[Vec<TokenValue>]) -> Option<Vec<Vec<i64>>> {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|v| match v {
+                    TokenValue::IntValue(i) => Some(*i),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The determinant of an int matrix using the Bareiss algorithm, which only ever divides evenly
+///
+/// Gives back `None` if a step is too big for an int.
+fn int_determinant(mut m: Vec<Vec<i64>>) -> Option<i64> {
+    let n = m.len();
+    let mut sign = 1;
+    let mut previous = 1;
+
+    for k in 0..n.saturating_sub(1) {
+        if m[k][k] == 0 {
+            match (k + 1..n).find(|&i| m[i][k] != 0) {
+                Some(i) => {
+                    m.swap(k, i);
+                    sign = -sign;
+                }
+                None => return Some(0),
+            }
+        }
+
+        for i in k + 1..n {
+            for j in k + 1..n {
+                let value = m[i][j]
+                    .checked_mul(m[k][k])?
+                    .checked_sub(m[i][k].checked_mul(m[k][j])?)?;
+                m[i][j] = value / previous;
+            }
+        }
+
+        previous = m[k][k];
+    }
+
+    match n {
+        0 => Some(1),
+        _ => m[n - 1][n - 1].checked_mul(sign),
+    }
+}
+
+/// The determinant using elimination with partial pivoting
+fn dec_determinant(mut m: Vec<Vec<f64>>) -> f64 {
+    let n = m.len();
+    let scale = largest(&m);
+    let mut det = 1.0;
+
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| m[i][k].abs().total_cmp(&m[j][k].abs()))
+            .expect("Range is not empty");
+
+        if m[pivot][k].abs() <= PIVOT_EPSILON * scale {
+            return 0.0;
+        }
+
+        if pivot != k {
+            m.swap(k, pivot);
+            det = -det;
+        }
+
+        det *= m[k][k];
+
+        let (top, bottom) = m.split_at_mut(k + 1);
+        let pivot_row = &top[k];
+        for row in bottom {
+            let factor = row[k] / pivot_row[k];
+            for (v, p) in row[k..].iter_mut().zip(&pivot_row[k..]) {
+                *v -= factor * p;
+            }
+        }
+    }
+
+    det
+}
+
+/// Solve `a x = b` for every column of `b` with Gauss-Jordan elimination
+///
+/// Gives back `None` when `a` is singular.
+fn eliminate(mut a: Vec<Vec<f64>>, mut b: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let scale = largest(&a);
+
+    for k in 0..n {
+        let pivot = (k..n).max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))?;
+
+        if a[pivot][k].abs() <= PIVOT_EPSILON * scale {
+            return None;
+        }
+
+        a.swap(k, pivot);
+        b.swap(k, pivot);
+
+        let p = a[k][k];
+        a[k].iter_mut().for_each(|v| *v /= p);
+        b[k].iter_mut().for_each(|v| *v /= p);
+
+        let (pivot_a, pivot_b) = (a[k].clone(), b[k].clone());
+        for i in (0..n).filter(|&i| i != k) {
+            let factor = a[i][k];
+            for (v, p) in a[i].iter_mut().zip(&pivot_a) {
+                *v -= factor * p;
+            }
+            for (v, p) in b[i].iter_mut().zip(&pivot_b) {
+                *v -= factor * p;
+            }
+        }
+    }
+
+    Some(b)
+}
+
+/// Solve `a x = b` exactly for every column of `b` with Gauss-Jordan elimination on ratios
+///
+/// Gives back `Some(None)` when `a` is singular, and `None` if a step is too big for a ratio.
+fn exact_eliminate(
+    mut a: Vec<Vec<Ratio>>,
+    mut b: Vec<Vec<Ratio>>,
+) -> Option<Option<Vec<Vec<Ratio>>>> {
+    let n = a.len();
+
+    for k in 0..n {
+        // Any entry that isn't zero works as a pivot when nothing is rounded
+        let pivot = match (k..n).find(|&i| !a[i][k].is_zero()) {
+            Some(i) => i,
+            None => return Some(None),
+        };
+
+        a.swap(k, pivot);
+        b.swap(k, pivot);
+
+        let p = a[k][k];
+        for v in a[k].iter_mut().chain(b[k].iter_mut()) {
+            *v = v.checked_div(p)?;
+        }
+
+        let (pivot_a, pivot_b) = (a[k].clone(), b[k].clone());
+        for i in (0..n).filter(|&i| i != k) {
+            let factor = a[i][k];
+            let (row_a, row_b) = (&mut a[i], &mut b[i]);
+            for (v, p) in row_a
+                .iter_mut()
+                .zip(&pivot_a)
+                .chain(row_b.iter_mut().zip(&pivot_b))
+            {
+                *v = v.checked_sub(factor.checked_mul(*p)?)?;
+            }
+        }
+    }
+
+    Some(Some(b))
+}
+
+/// Whole ratios are given back as ints, like `inv` of `((1 0) (0 1))` is `((1 0) (0 1))`
+fn exact_rows(rows: Vec<Vec<Ratio>>) -> Vec<Vec<TokenValue>> {
+    rows.into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|r| match r.denom() {
+                    1 => TokenValue::IntValue(r.numer()),
+                    _ => TokenValue::RatioValue(r),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Solve `a x = b` for every column of `b`, exactly when every entry is an int or a ratio
+///
+/// Gives back `None` when `a` is singular.
+fn solved(a: &[Vec<TokenValue>], b: &[Vec<TokenValue>]) -> Option<Vec<Vec<TokenValue>>> {
+    let exact = to_ratio(a).zip(to_ratio(b));
+
+    match exact.and_then(|(a, b)| exact_eliminate(a, b)) {
+        Some(x) => x.map(exact_rows),
+        None => eliminate(to_f64(a), to_f64(b)).map(dec_rows),
+    }
+}
+
+fn dec_rows(rows: Vec<Vec<f64>>) -> Vec<Vec<TokenValue>> {
+    rows.into_iter()
+        .map(|row| row.into_iter().map(TokenValue::DecValue).collect())
+        .collect()
+}
+
+/// Swap the rows and columns of a matrix
+pub fn transpose(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let rows = matrix(&a)?;
+    Ok(Value::matrix(transposed(&rows), a.span.to(operation.span)).with_unit(a.unit))
+}
+
+/// The determinant of a square matrix
+pub fn det(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let rows = square(&a)?;
+    let span = a.span.to(operation.span);
+    let unit = match a.unit.powi(rows.len() as i32) {
+        Some(unit) => unit,
+        None => return Err(too_big(vec![a])),
+    };
+
+    match to_i64(&rows).and_then(int_determinant) {
+        Some(d) => Ok(Value::int(d, span).with_unit(unit)),
+        None => Ok(Value::dec(dec_determinant(to_f64(&rows)), span).with_unit(unit)),
+    }
+}
+
+/// The inverse of a square matrix
+pub fn inv(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let rows = square(&a)?;
+    let n = rows.len();
+
+    let identity: Vec<Vec<TokenValue>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| TokenValue::IntValue((i == j) as i64))
+                .collect()
+        })
+        .collect();
+
+    let unit = match a.unit.powi(-1) {
+        Some(unit) => unit,
+        None => return Err(too_big(vec![a])),
+    };
+
+    match solved(&rows, &identity) {
+        Some(inverse) => Ok(Value::matrix(inverse, a.span.to(operation.span)).with_unit(unit)),
+        None => Err(ComponentError::SingularMatrix { value: Box::new(a) }),
+    }
+}
+
+/// Solve `a x = b` for the vector `x`
+pub fn solve(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let rows = square(&a)?;
+
+    let column: Vec<Vec<TokenValue>> = match (&b.value_type, b.components()) {
+        (TokenType::VectorLiteral, Some(c)) => c.iter().map(|v| vec![v.clone()]).collect(),
+        _ => {
+            return Err(ComponentError::WrongType {
+                values: vec![b],
+                expected: vec![TokenType::VectorLiteral],
+            })
+        }
+    };
+
+    if column.len() != rows.len() {
+        return Err(ComponentError::SizeMismatch {
+            values: vec![a, b],
+            expected: None,
+        });
+    }
+
+    let unit = match b.unit.div(&a.unit) {
+        Some(unit) => unit,
+        None => return Err(too_big(vec![a, b])),
+    };
+
+    match solved(&rows, &column) {
+        Some(x) => Ok(
+            Value::vector(x.into_iter().flatten().collect(), a.span.to(operation.span))
+                .with_unit(unit),
+        ),
+        None => Err(ComponentError::SingularMatrix { value: Box::new(a) }),
+    }
+}
+
+/// The `n` by `n` identity matrix
+pub fn identity(n: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let size = match n.value {
+        TokenValue::IntValue(i) => i,
+        _ => {
+            return Err(ComponentError::WrongType {
+                values: vec![n],
+                expected: vec![TokenType::NumericIntLiteral],
+            })
+        }
+    };
+
+    if size < 1 {
+        return Err(ComponentError::SizeMismatch {
+            values: vec![n],
+            expected: Some("at least 1".to_string()),
+        });
+    }
+
+    if size > MAX_IDENTITY_SIZE {
+        return Err(ComponentError::SizeMismatch {
+            values: vec![n],
+            expected: Some(format!("at most {}", MAX_IDENTITY_SIZE)),
+        });
+    }
+
+    let size = size as usize;
+    let rows = (0..size)
+        .map(|i| {
+            (0..size)
+                .map(|j| TokenValue::IntValue((i == j) as i64))
+                .collect()
+        })
+        .collect();
+
+    Ok(Value::matrix(rows, n.span.to(operation.span)))
+}
@@ -1,9 +1,19 @@
-use super::display::{
-    invalid_type_cast_error, stack_empty_error, wrong_type_error_both, wrong_type_error_first,
-    wrong_type_error_second,
+use super::complex::{abs, arg, complex_of, conj, im, re, sqrt, Complex};
+use super::error::ComponentError;
+use super::lexer::{Span, Token, TokenType, TokenValue};
+use super::logic::{compare, logic, not, truth};
+use super::math::{
+    acos, asin, atan, atan2, cos, cosh, exp, fact, gcd, lcm, ln, log, log2, max, min, modulo, sin,
+    sinh, tan, tanh,
 };
-use super::lexer::{Token, TokenType};
+use super::matrix::{det, identity, inv, solve, transpose};
+use super::ratio::Ratio;
+use super::round::{round, round_to, RoundMode, Rounding};
+use super::unit::{attach, convert};
+use super::value::{arithmetic, cross, dot, get, len, norm, numeric_types, Value};
 use efcl::{bold, color, Color};
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
 use std::collections::HashMap;
 
 #[derive(Debug, Copy, Clone)]
@@ -12,64 +22,530 @@ pub enum AssemblyArchitecture {
     RISCV,
 }
 
+/// How a built-in function is called, with one or two values from the stack
+#[derive(Clone, Copy)]
+enum BuiltinCall {
+    Unary(fn(Value, &Token) -> Result<Value, ComponentError>),
+    Binary(fn(Value, Value, &Token) -> Result<Value, ComponentError>),
+}
+
+/// A function like `sin` that is called by its name, which can't be used for variables or functions
+struct Builtin {
+    name: &'static str,
+    about: &'static str,
+    call: BuiltinCall,
+}
+
+const BUILTINS: [Builtin; 21] = [
+    Builtin {
+        name: "sin",
+        about: "sine",
+        call: BuiltinCall::Unary(sin),
+    },
+    Builtin {
+        name: "cos",
+        about: "cosine",
+        call: BuiltinCall::Unary(cos),
+    },
+    Builtin {
+        name: "tan",
+        about: "tangent",
+        call: BuiltinCall::Unary(tan),
+    },
+    Builtin {
+        name: "asin",
+        about: "inverse sine",
+        call: BuiltinCall::Unary(asin),
+    },
+    Builtin {
+        name: "acos",
+        about: "inverse cosine",
+        call: BuiltinCall::Unary(acos),
+    },
+    Builtin {
+        name: "atan",
+        about: "inverse tangent",
+        call: BuiltinCall::Unary(atan),
+    },
+    Builtin {
+        name: "atan2",
+        about: "angle of the point x y, written y x atan2",
+        call: BuiltinCall::Binary(atan2),
+    },
+    Builtin {
+        name: "sinh",
+        about: "hyperbolic sine",
+        call: BuiltinCall::Unary(sinh),
+    },
+    Builtin {
+        name: "cosh",
+        about: "hyperbolic cosine",
+        call: BuiltinCall::Unary(cosh),
+    },
+    Builtin {
+        name: "tanh",
+        about: "hyperbolic tangent",
+        call: BuiltinCall::Unary(tanh),
+    },
+    Builtin {
+        name: "ln",
+        about: "natural log",
+        call: BuiltinCall::Unary(ln),
+    },
+    Builtin {
+        name: "log",
+        about: "log base 10",
+        call: BuiltinCall::Unary(log),
+    },
+    Builtin {
+        name: "log2",
+        about: "log base 2",
+        call: BuiltinCall::Unary(log2),
+    },
+    Builtin {
+        name: "exp",
+        about: "e to a power",
+        call: BuiltinCall::Unary(exp),
+    },
+    Builtin {
+        name: "abs",
+        about: "absolute value or magnitude",
+        call: BuiltinCall::Unary(abs),
+    },
+    Builtin {
+        name: "min",
+        about: "smaller of two numbers",
+        call: BuiltinCall::Binary(min),
+    },
+    Builtin {
+        name: "max",
+        about: "larger of two numbers",
+        call: BuiltinCall::Binary(max),
+    },
+    Builtin {
+        name: "mod",
+        about: "remainder with the sign of the divisor",
+        call: BuiltinCall::Binary(modulo),
+    },
+    Builtin {
+        name: "gcd",
+        about: "greatest common divisor",
+        call: BuiltinCall::Binary(gcd),
+    },
+    Builtin {
+        name: "lcm",
+        about: "least common multiple",
+        call: BuiltinCall::Binary(lcm),
+    },
+    Builtin {
+        name: "fact",
+        about: "factorial",
+        call: BuiltinCall::Unary(fact),
+    },
+];
+
+fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+/// Whether the `>` at `index` starts a function instead of comparing two values
+///
+/// A function starts with the `>` at the start of a line, so its body can go on over several
+/// lines, or with a `>` that has an `fn` after it on the same line.
+fn opens_function(tokens: &[Token], index: usize) -> bool {
+    let line_start = match index.checked_sub(1) {
+        Some(i) => tokens[i].token_type == TokenType::Newline,
+        None => true,
+    };
+
+    line_start
+        || tokens[index + 1..]
+            .iter()
+            .take_while(|t| t.token_type != TokenType::Newline)
+            .any(|t| t.token_type == TokenType::Function)
+}
+
+/// Split the tokens of a program into the statements on each line
+///
+/// A statement goes on past the end of its line while a function, parenthesis or square bracket
+/// is still open, so each statement can be run on its own.
+pub fn split_statements(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut in_function = false;
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::Newline if !in_function && depth <= 0 => {
+                if !current.is_empty() {
+                    statements.push(std::mem::take(&mut current));
+                }
+                depth = 0;
+                continue;
+            }
+            TokenType::Function if in_function => in_function = false,
+            TokenType::Greater if !in_function && opens_function(tokens, i) => in_function = true,
+            TokenType::LeftParen | TokenType::LeftBracket if !in_function => depth += 1,
+            TokenType::RightParen | TokenType::RightBracket if !in_function => depth -= 1,
+            _ => {}
+        }
+
+        current.push(token.clone());
+    }
+
+    if !current.is_empty() {
+        statements.push(current);
+    }
+
+    statements
+}
+
+/// A token made while running, written where `at` was
+fn made_token(token_type: TokenType, value: &str, at: &Token) -> Token {
+    Token {
+        token_type,
+        value: value.to_string(),
+        span: at.span,
+    }
+}
+
+/// The body of a function written where it is called from, so errors in it point at the call
+/// instead of a column of the line the function was defined on
+fn respanned(body: &[Token], at: &Token) -> Vec<Token> {
+    body.iter()
+        .map(|t| made_token(t.token_type.clone(), &t.value, at))
+        .collect()
+}
+
+/// The tokens of a quotation with its square brackets put back, so it can be pushed again
+fn quoted(body: &[Token], at: &Token) -> Vec<Token> {
+    let mut tokens = vec![made_token(TokenType::LeftBracket, "[", at)];
+    tokens.extend_from_slice(body);
+    tokens.push(made_token(TokenType::RightBracket, "]", at));
+    tokens
+}
+
 pub trait Parser {
-    fn variable_check_pop(&mut self) -> Option<Token>;
-    fn match_token_type(&mut self, token: Token);
-    fn parse(&mut self, tokens: Vec<Token>) -> Token;
-    fn assign_value(&mut self, first: Option<Token>, second: Option<Token>, token: Token);
-    fn convert_to_bool(&mut self, first: Option<Token>, token: Token);
+    fn variable_check_pop(&mut self) -> Option<Value>;
+    fn resolve_variable(&self, value: Value) -> Value;
+    fn match_token_type(&mut self, token: Token) -> Result<(), ComponentError>;
+    fn parse(&mut self, tokens: Vec<Token>) -> Result<Value, ComponentError>;
+    fn run_tokens(&mut self, tokens: Vec<Token>) -> Result<Value, ComponentError>;
+    fn assign_value(
+        &mut self,
+        first: Option<Value>,
+        second: Option<Value>,
+        token: Token,
+    ) -> Result<(), ComponentError>;
+    fn convert_to_bool(&mut self, first: Option<Value>, token: Token)
+        -> Result<(), ComponentError>;
     fn asm_li(&mut self, token: Token);
     fn asm_add(&mut self);
     fn asm_sub(&mut self);
     fn output_asm(&mut self) -> Vec<String>;
     fn reset_asm(&mut self);
     fn set_asm_arch(&mut self, assembly_arch: AssemblyArchitecture);
-    fn convert_to_int(&mut self, token: Token);
+    fn convert_to_int(&mut self, token: Token) -> Result<(), ComponentError>;
+    fn set_round_mode(&mut self, round_mode: RoundMode);
+    fn stack_word(&mut self, token: Token) -> Result<(), ComponentError>;
+    fn starts_function(&self) -> bool;
+    fn quotation(&mut self, token: Token) -> Result<(), ComponentError>;
+    fn run_next(&mut self, tokens: Vec<Token>);
+    fn control_word(&mut self, token: Token) -> Result<(), ComponentError>;
+    fn pop_quotation(&mut self, token: &Token) -> Result<Vec<Token>, ComponentError>;
+    fn pop_int(&mut self, token: &Token) -> Result<i64, ComponentError>;
+    fn loop_word(&mut self, token: Token) -> Result<(), ComponentError>;
 }
 
 #[derive(Debug)]
 pub struct ParserState {
     function_mode: bool,
     verbose: bool,
-    stack: Vec<Token>,
+    stack: Vec<Value>,
     token_stack: Vec<Token>,
-    local_memory: HashMap<String, Token>,
+    local_memory: HashMap<String, Value>,
     function_stack: Vec<Token>,
+    // Where the stack was at each open parenthesis, so the values since then make up the vector
+    paren_stack: Vec<(usize, Token)>,
     function_memory: HashMap<String, Vec<Token>>,
     token_index: usize,
     assembly: Vec<String>,
     assembly_arch: AssemblyArchitecture,
-    temp_reg_index: i8,
-    temp_reg_index_init: i8,
+    temp_reg_index: i64,
+    temp_reg_index_init: i64,
+    round_mode: RoundMode,
 }
 
 impl Parser for ParserState {
-    fn convert_to_int(&mut self, token: Token) {
+    fn convert_to_int(&mut self, token: Token) -> Result<(), ComponentError> {
         let first = self.variable_check_pop();
 
         match first {
-            Some(a) => match a.token_type {
-                TokenType::NumericIntLiteral
-                | TokenType::NumericDecLiteral
-                | TokenType::BoolLiteral => {
-                    let a_float_res = a.value.parse::<f64>();
-                    match a_float_res {
-                        Ok(a_val) => self.stack.push(Token {
-                            token_type: TokenType::NumericIntLiteral,
-                            value: (a_val as i64).to_string(),
-                        }),
-                        Err(_) => wrong_type_error_first(a.value, token.value),
+            Some(a) => {
+                let span = a.span.to(token.span);
+                let unit = a.unit.clone();
+
+                let out = match a.value {
+                    TokenValue::IntValue(v) => Value::int(v, span),
+                    TokenValue::BigIntValue(v) => Value::big_int(v, span),
+                    // Decimals past the size of an i64 still have a whole part
+                    TokenValue::DecValue(v) if v.is_finite() => match BigInt::from_f64(v) {
+                        Some(big) => Value::big_int(big, span),
+                        None => Value::int(v as i64, span),
+                    },
+                    TokenValue::RatioValue(v) => Value::int(v.numer() / v.denom(), span),
+                    TokenValue::BoolValue(b) => Value::int(b as i64, span),
+                    _ => {
+                        return Err(ComponentError::InvalidTypeCast {
+                            value: Box::new(a),
+                            cast: Box::new(token),
+                            cast_to: TokenType::NumericIntLiteral,
+                        })
                     }
+                };
+
+                self.stack.push(out.with_unit(unit));
+            }
+            None => return Err(ComponentError::StackEmpty { operation: token }),
+        }
+
+        Ok(())
+    }
+
+    /// Whether the current `>` starts a function instead of comparing two values
+    fn starts_function(&self) -> bool {
+        opens_function(&self.token_stack, self.token_index)
+    }
+
+    /// Push the tokens up to the matching `]` as a quotation without running them
+    ///
+    /// Quotations can hold other quotations and go on over several lines like functions can.
+    fn quotation(&mut self, token: Token) -> Result<(), ComponentError> {
+        let mut depth = 0;
+        let mut body = Vec::new();
+
+        for (i, t) in self
+            .token_stack
+            .iter()
+            .enumerate()
+            .skip(self.token_index + 1)
+        {
+            match t.token_type {
+                TokenType::RightBracket if depth == 0 => {
+                    let span = token.span.to(t.span);
+                    self.stack.push(Value::quotation(body, span));
+                    self.token_index = i;
+                    return Ok(());
+                }
+                TokenType::LeftBracket => depth += 1,
+                TokenType::RightBracket => depth -= 1,
+                TokenType::Newline => continue,
+                _ => {}
+            }
+
+            body.push(t.clone());
+        }
+
+        Err(ComponentError::UnbalancedParentheses { paren: token })
+    }
+
+    /// Run tokens straight after the current one, before the rest of the line
+    fn run_next(&mut self, tokens: Vec<Token>) {
+        // Loops keep adding tokens, so the ones already run are let go of once they are over half
+        // of them. The current token is kept since `>` looks at the token before it.
+        if self.token_index > self.token_stack.len() / 2 {
+            self.token_stack.drain(..self.token_index);
+            self.token_index = 0;
+        }
+
+        let at = self.token_index + 1;
+        self.token_stack.splice(at..at, tokens);
+    }
+
+    fn pop_quotation(&mut self, token: &Token) -> Result<Vec<Token>, ComponentError> {
+        match self.variable_check_pop() {
+            Some(Value {
+                value: TokenValue::QuotationValue(body),
+                ..
+            }) => Ok(body),
+            Some(other) => Err(ComponentError::WrongType {
+                values: vec![other],
+                expected: vec![TokenType::QuotationLiteral],
+            }),
+            None => Err(ComponentError::StackEmpty {
+                operation: token.clone(),
+            }),
+        }
+    }
+
+    fn pop_int(&mut self, token: &Token) -> Result<i64, ComponentError> {
+        match self.variable_check_pop() {
+            Some(Value {
+                value: TokenValue::IntValue(v),
+                unit,
+                ..
+            }) if unit.is_none() => Ok(v),
+            Some(other) => Err(ComponentError::WrongType {
+                values: vec![other],
+                expected: vec![TokenType::NumericIntLiteral],
+            }),
+            None => Err(ComponentError::StackEmpty {
+                operation: token.clone(),
+            }),
+        }
+    }
+
+    /// Run a quotation over and over with `times`, `while` and `for`
+    ///
+    /// Each loop runs its quotation once and then puts itself back after it with what is left to
+    /// do, so `3 [1 +] times` becomes `1 + 2 [1 +] times`.
+    ///
+    /// - `n [..] times` runs the quotation `n` times
+    /// - `[..] [..] while` runs the second quotation for as long as the first one gives true
+    /// - `a b [..] for` runs the quotation with each int from `a` up to `b` pushed before it
+    fn loop_word(&mut self, token: Token) -> Result<(), ComponentError> {
+        let body = self.pop_quotation(&token)?;
+        let int = |v: i64| made_token(TokenType::NumericIntLiteral, &v.to_string(), &token);
+
+        let next = match token.token_type {
+            TokenType::TimesKeyword => {
+                let n = self.pop_int(&token)?;
+                if n <= 0 {
+                    return Ok(());
                 }
 
-                _ => invalid_type_cast_error(String::from("NumericIntLiteral"), a, token),
+                let mut next = body.clone();
+                next.push(int(n - 1));
+                next.extend(quoted(&body, &token));
+                next.push(token.clone());
+                next
+            }
+            // `[c] [b] while` is `c [b [c] [b] while] if`
+            TokenType::WhileKeyword => {
+                let condition = self.pop_quotation(&token)?;
+
+                let mut repeat = body.clone();
+                repeat.extend(quoted(&condition, &token));
+                repeat.extend(quoted(&body, &token));
+                repeat.push(token.clone());
+
+                let mut next = condition;
+                next.extend(quoted(&repeat, &token));
+                next.push(made_token(TokenType::IfKeyword, "if", &token));
+                next
+            }
+            _ => {
+                let end = self.pop_int(&token)?;
+                let start = self.pop_int(&token)?;
+                if start > end {
+                    return Ok(());
+                }
+
+                let mut next = vec![int(start)];
+                next.extend(body.iter().cloned());
+                if start < end {
+                    next.push(int(start + 1));
+                    next.push(int(end));
+                    next.extend(quoted(&body, &token));
+                    next.push(token.clone());
+                }
+                next
+            }
+        };
+
+        self.run_next(next);
+        Ok(())
+    }
+
+    /// Run quotations with `call`, `if` and `ifelse`
+    ///
+    /// `[2 *] call` always runs the quotation, `x [neg] if` runs it when `x` is true, and
+    /// `x [1] [2] ifelse` runs the first when `x` is true and the second when it isn't. True is the
+    /// same as with `bool`, so any number that isn't 0.
+    fn control_word(&mut self, token: Token) -> Result<(), ComponentError> {
+        let count = match token.token_type {
+            TokenType::IfElseKeyword => 2,
+            _ => 1,
+        };
+
+        let mut bodies = Vec::new();
+        for _ in 0..count {
+            bodies.push(self.pop_quotation(&token)?);
+        }
+
+        // Popped from the top, so the quotation for false comes first
+        let body = if token.token_type == TokenType::CallKeyword {
+            bodies.pop()
+        } else {
+            let condition = match self.variable_check_pop() {
+                Some(condition) => truth(&condition)?,
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            };
+
+            match (condition, count) {
+                (true, _) => bodies.pop(),
+                (false, 2) => bodies.first().cloned(),
+                _ => None,
+            }
+        };
+
+        if let Some(body) = body {
+            self.run_next(body);
+        }
+
+        Ok(())
+    }
+
+    /// Move values around on the stack without giving them names, like the words in Forth
+    ///
+    /// `pick` and `roll` count down from the top of the stack starting at 0, so `0 pick` is `dup`,
+    /// `1 pick` is `over`, `1 roll` is `swap` and `2 roll` is `rot`.
+    fn stack_word(&mut self, token: Token) -> Result<(), ComponentError> {
+        // How far down the stack the word reaches
+        let reach = match token.token_type {
+            TokenType::PickKeyword | TokenType::RollKeyword => match self.variable_check_pop() {
+                Some(n) => match n.value {
+                    TokenValue::IntValue(i) if i >= 0 => i as usize + 1,
+                    _ => {
+                        return Err(ComponentError::WrongType {
+                            values: vec![n],
+                            expected: vec![TokenType::NumericIntLiteral],
+                        })
+                    }
+                },
+                None => return Err(ComponentError::StackEmpty { operation: token }),
             },
-            None => stack_empty_error(),
+            TokenType::DupKeyword | TokenType::DropKeyword => 1,
+            TokenType::SwapKeyword | TokenType::OverKeyword => 2,
+            TokenType::RotKeyword => 3,
+            _ => 0,
+        };
+
+        if reach > self.stack.len() {
+            return Err(ComponentError::StackEmpty { operation: token });
+        }
+
+        let len = self.stack.len();
+        match token.token_type {
+            TokenType::DupKeyword => self.stack.push(self.stack[len - 1].clone()),
+            TokenType::DropKeyword => {
+                self.stack.pop();
+            }
+            TokenType::SwapKeyword => self.stack.swap(len - 1, len - 2),
+            TokenType::OverKeyword => self.stack.push(self.stack[len - 2].clone()),
+            TokenType::RotKeyword => self.stack[len - 3..].rotate_left(1),
+            TokenType::PickKeyword => self.stack.push(self.stack[len - reach].clone()),
+            TokenType::RollKeyword => self.stack[len - reach..].rotate_left(1),
+            TokenType::ClearKeyword => self.stack.clear(),
+            _ => self.stack.push(Value::int(len as i64, token.span)),
         }
+
+        Ok(())
     }
 
     fn output_asm(&mut self) -> Vec<String> {
-        return self.assembly.clone();
+        self.assembly.clone()
     }
 
     fn set_asm_arch(&mut self, assembly_arch: AssemblyArchitecture) {
@@ -86,104 +562,99 @@ impl Parser for ParserState {
         }
     }
 
+    fn set_round_mode(&mut self, round_mode: RoundMode) {
+        self.round_mode = round_mode;
+    }
+
     fn reset_asm(&mut self) {
         self.assembly = vec![];
         self.temp_reg_index = self.temp_reg_index_init;
     }
 
-    fn convert_to_bool(&mut self, first: Option<Token>, token: Token) {
+    fn convert_to_bool(
+        &mut self,
+        first: Option<Value>,
+        token: Token,
+    ) -> Result<(), ComponentError> {
         match first {
-            Some(a) => match a.token_type {
-                TokenType::NumericIntLiteral
-                | TokenType::NumericDecLiteral
-                | TokenType::BoolLiteral => {
-                    let a_float_res = a.value.parse::<f64>();
-                    match a_float_res {
-                        Ok(a_val) => self.stack.push(Token {
-                            token_type: TokenType::BoolLiteral,
-                            value: ((a_val != 0.0) as i32).to_string(),
-                        }),
-                        Err(_) => wrong_type_error_first(a.value, token.value),
-                    }
+            Some(a) => match a.as_f64() {
+                Some(a_val) => self
+                    .stack
+                    .push(Value::boolean(a_val != 0.0, a.span.to(token.span))),
+                None => {
+                    return Err(ComponentError::InvalidTypeCast {
+                        value: Box::new(a),
+                        cast: Box::new(token),
+                        cast_to: TokenType::BoolLiteral,
+                    })
                 }
-                _ => invalid_type_cast_error(String::from("BoolLiteral"), a, token),
-            },
-            None => stack_empty_error(),
-        }
-    }
-
-    fn assign_value(&mut self, first: Option<Token>, second: Option<Token>, token: Token) {
-        match (first, second) {
-            (Some(a), Some(b)) => {
-                // Does the variable already exist?
-                match self.local_memory.get(&b.value) {
-                    Some(tok) => {
-                        // Assigning to the same type as the existing variable
-                        if tok.token_type == a.token_type {
-                            // Write variable to memory
-                            let out = Token {
-                                token_type: a.token_type,
-                                value: a.value,
-                            };
-                            self.local_memory.insert(b.value, out.clone());
-                            self.stack.push(out);
-                        } else {
-                            println!(
-                                "{} Assignment Type Mismatch [E1]",
-                                color!(Color::RED, bold!("Error:").as_str()).as_str()
-                            );
-                            println!(
-                                "{} {} {}",
-                                a.value,
-                                color!(Color::RED, bold!(b.value.as_str()).as_str()),
-                                token.value
-                            );
-                            println!(
-                                "{}{} cannot assign value {} of type <{:?}> to a variable of type <{:?}>",
-                                (0..a.value.len() + 1).map(|_| " ").collect::<String>(),
-                                color!(
-                                    Color::RED,
-                                    bold!(&(0..b.value.len())
-                                        .map(|_| "^")
-                                        .collect::<String>())
-                                    .as_str()
-                                ),
-                                a.value,
-                                a.token_type,
-                                tok.token_type,
-                            );
-                        }
-                    }
-                    None => {
-                        // Write variable to memory
-                        let out = Token {
-                            token_type: a.token_type,
-                            value: a.value,
-                        };
-                        self.local_memory.insert(b.value, out.clone());
-                        self.stack.push(out);
-                    }
+            },
+            None => return Err(ComponentError::StackEmpty { operation: token }),
+        }
+
+        Ok(())
+    }
+
+    fn assign_value(
+        &mut self,
+        first: Option<Value>,
+        second: Option<Value>,
+        token: Token,
+    ) -> Result<(), ComponentError> {
+        let span = token.span;
+
+        if let (Some(a), Some(b)) = (first, second) {
+            let name = match b.name() {
+                Some(name) => name.to_string(),
+                None => {
+                    return Err(ComponentError::WrongType {
+                        values: vec![b],
+                        expected: vec![TokenType::Identifier],
+                    })
+                }
+            };
+
+            // Does the variable already exist?
+            if let Some(var) = self.local_memory.get(&name) {
+                // Only assign to the same type as the existing variable
+                if var.value_type != a.value_type {
+                    let variable_type = var.value_type.clone();
+                    return Err(ComponentError::AssignmentTypeMismatch {
+                        value: Box::new(a),
+                        variable: Box::new(b),
+                        variable_type,
+                    });
                 }
             }
-            _ => {}
+
+            // Write variable to memory
+            let out = Value {
+                span: a.span.to(span),
+                ..a
+            };
+            self.local_memory.insert(name, out.clone());
+            self.stack.push(out);
         }
+
+        Ok(())
     }
 
-    fn variable_check_pop(&mut self) -> Option<Token> {
+    fn variable_check_pop(&mut self) -> Option<Value> {
         let first = self.stack.pop();
 
-        if let Some(ref a) = first {
-            match self.local_memory.get(&a.value) {
-                Some(tok) => {
-                    return Some(tok.clone());
-                }
-                None => {
-                    return first;
-                }
+        first.map(|a| self.resolve_variable(a))
+    }
+
+    fn resolve_variable(&self, value: Value) -> Value {
+        match value.name().and_then(|name| self.local_memory.get(name)) {
+            Some(var) => {
+                // Point at where the variable was used rather than where it was assigned
+                let mut var = var.clone();
+                var.span = value.span;
+                var
             }
+            None => value,
         }
-
-        return first;
     }
 
     fn asm_li(&mut self, token: Token) {
@@ -245,24 +716,62 @@ impl Parser for ParserState {
         }
     }
 
-    fn match_token_type(&mut self, token: Token) {
+    fn match_token_type(&mut self, token: Token) -> Result<(), ComponentError> {
         // Match the type of token
         // if it's a literal, add it to the stack
         // if it's an operation, pop values from the stack and apply the operation
         match token.token_type {
-            TokenType::Greater => {
+            TokenType::Greater if self.starts_function() => {
                 self.function_mode = true;
             }
 
-            TokenType::NumericIntLiteral => {
-                self.asm_li(token.clone());
-                self.stack.push(token);
+            TokenType::Greater
+            | TokenType::Less
+            | TokenType::GreaterEqual
+            | TokenType::LessEqual
+            | TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::AndKeyword
+            | TokenType::OrKeyword
+            | TokenType::XorKeyword => {
+                let second = self.variable_check_pop();
+                let first = self.variable_check_pop();
+
+                match (first, second) {
+                    (Some(a), Some(b)) => {
+                        let out = match token.token_type {
+                            TokenType::AndKeyword
+                            | TokenType::OrKeyword
+                            | TokenType::XorKeyword => logic(a, b, &token)?,
+                            _ => compare(a, b, &token)?,
+                        };
+                        self.stack.push(out);
+                    }
+                    _ => return Err(ComponentError::StackEmpty { operation: token }),
+                }
+            }
+
+            TokenType::NumericIntLiteral
+            | TokenType::NumericDecLiteral
+            | TokenType::ImaginaryLiteral
+            | TokenType::StringLiteral => {
+                if token.token_type == TokenType::NumericIntLiteral {
+                    self.asm_li(token.clone());
+                }
+
+                match Value::from_token(&token) {
+                    Some(value) => self.stack.push(value),
+                    None => return Err(ComponentError::InvalidLiteral { literal: token }),
+                }
             }
 
-            TokenType::NumericDecLiteral => {
-                self.stack.push(token);
+            TokenType::MalformedLiteral => {
+                return Err(ComponentError::InvalidLiteral { literal: token })
             }
 
+            // Lines in a program only separate statements
+            TokenType::Newline => {}
+
             TokenType::DoubleQuestion => {
                 println!("\nGuide: ");
                 println!("------------------------------------------------------");
@@ -279,7 +788,7 @@ impl Parser for ParserState {
                 println!("Try typing the following into the interactive prompt:");
                 println!("\n\t{}\n", bold!("1 2 +"));
                 println!("It should look this this once it's been typed in and you press enter:");
-                println!("{}{}", color!(Color::GREEN, bold!("> ").as_str()), "1 2 +");
+                println!("{}1 2 +", color!(Color::GREEN, bold!("> ").as_str()));
                 println!(
                     "{}{}",
                     color!(Color::GREEN, bold!("-> ").as_str()),
@@ -290,68 +799,131 @@ impl Parser for ParserState {
                 println!("{} subtraction", bold!("-"));
                 println!("{} mltiplication", bold!("*"));
                 println!("{} division", bold!("/"));
+                println!("{} division rounded down to an int", bold!("//"));
+                println!("{} remainder", bold!("%"));
                 println!("{} exponentiation", bold!("^"));
                 println!("\nSome that may be familar from other languages:");
                 println!("{} logical not", bold!("!"));
+                println!("{} comparisons", bold!("< > <= >= == !="));
+                println!("{} logic", bold!("and or xor"));
                 println!("\nAnd some that may not be as familar:");
                 println!("{} conditional assignment", bold!("?"));
+                println!(
+                    "\nCode in square brackets like {} is kept to run later:",
+                    bold!("[2 *]")
+                );
+                println!("{} run a quotation", bold!("call"));
+                println!("{} run a quotation if a value is true", bold!("if"));
+                println!(
+                    "{} run the first quotation if a value is true or else the second",
+                    bold!("ifelse")
+                );
+                println!("{} run a quotation a number of times", bold!("times"));
+                println!(
+                    "{} run the second quotation for as long as the first gives true",
+                    bold!("while")
+                );
+                println!(
+                    "{} run a quotation with each int from one to another",
+                    bold!("for")
+                );
+                println!("\nStack words move values around without naming them:");
+                println!("{} copy the top value", bold!("dup"));
+                println!("{} throw away the top value", bold!("drop"));
+                println!("{} swap the top two values", bold!("swap"));
+                println!("{} copy the second value to the top", bold!("over"));
+                println!("{} move the third value to the top", bold!("rot"));
+                println!("{} copy the nth value down to the top", bold!("pick"));
+                println!("{} move the nth value down to the top", bold!("roll"));
+                println!("{} throw away every value", bold!("clear"));
+                println!("{} number of values on the stack", bold!("depth"));
                 println!("\nComponent also has functions that can be called in the same was as operators:");
                 println!("{} square root", bold!("sqrt"));
+                println!("{} negation", bold!("neg"));
+                println!("{} round to the nearest int", bold!("round"));
+                println!("{} round down", bold!("floor"));
+                println!("{} round up", bold!("ceil"));
+                println!("{} round towards zero", bold!("trunc"));
+                println!("{} round to a number of decimal places", bold!("round-to"));
+                println!("\nAnd math functions:");
+                for builtin in &BUILTINS {
+                    println!("{} {}", bold!(builtin.name), builtin.about);
+                }
+                println!(
+                    "\nVectors are written in parentheses like {}",
+                    bold!("(1 2 3)")
+                );
+                println!("{} dot product", bold!("dot"));
+                println!("{} cross product", bold!("cross"));
+                println!("{} length of a vector", bold!("norm"));
+                println!("{} component at an index", bold!("get"));
+                println!(
+                    "\nMatrices are vectors of rows like {}",
+                    bold!("((1 2) (3 4))")
+                );
+                println!("{} swap rows and columns", bold!("transpose"));
+                println!("{} determinant", bold!("det"));
+                println!("{} inverse", bold!("inv"));
+                println!("{} solve Ax=b", bold!("solve"));
+                println!("{} n by n identity matrix", bold!("identity"));
+                println!(
+                    "\nImaginary numbers are written with an i like {}",
+                    bold!("3i")
+                );
+                println!("{} real part", bold!("re"));
+                println!("{} imaginary part", bold!("im"));
+                println!("{} complex conjugate", bold!("conj"));
+                println!("{} angle from the real axis", bold!("arg"));
+                println!(
+                    "\nUnits are written in braces after a value like {}",
+                    bold!("300 {m/s}")
+                );
+                println!("{} convert to the unit after it", bold!("to"));
+                println!(
+                    "\nStrings are written in quotes like {}",
+                    bold!("\"time: \"")
+                );
+                println!("{} join strings", bold!("+"));
+                println!("{} number of characters", bold!("len"));
+                println!("{} print a value", bold!("print"));
                 println!("\nAnd build in constants:");
                 println!("{} Euler's number", bold!("e"));
                 println!("{} Speed of light in m/s", bold!("C"));
                 println!("{} Pi", bold!("pi"));
                 println!("{} and {}", bold!("true"), bold!("false"));
+                println!("\nAnd types that values can be converted to:");
+                println!("{} integer", bold!("int"));
+                println!("{} decimal", bold!("dec"));
+                println!("{} exact fraction", bold!("ratio"));
+                println!("{} complex number", bold!("complex"));
+                println!("{} imaginary part of a complex number", bold!("imaginary"));
+                println!("{} boolean", bold!("bool"));
             }
 
-            TokenType::BoolLiteral => match token.value.as_str() {
-                "true" => self.stack.push(Token {
-                    token_type: TokenType::BoolLiteral,
-                    value: "1".to_string(),
-                }),
-                "false" => self.stack.push(Token {
-                    token_type: TokenType::BoolLiteral,
-                    value: "0".to_string(),
-                }),
-                _ => {}
-            },
-
-            TokenType::Bang => {
-                let first = self.variable_check_pop();
-
-                self.convert_to_bool(first, token);
-                let second = self.variable_check_pop();
-
-                match second {
-                    Some(b) => {
-                        let a_float = b
-                            .value
-                            .parse::<f64>()
-                            .expect("Bool should always be readable here");
-
-                        self.stack.push(Token {
-                            token_type: TokenType::BoolLiteral,
-                            value: (a_float == 0.0).to_string(),
-                        });
-                    }
-                    None => stack_empty_error(),
-                }
+            TokenType::BoolLiteral => {
+                self.stack
+                    .push(Value::boolean(token.value == "true", token.span));
             }
 
+            TokenType::Bang => match self.variable_check_pop() {
+                Some(a) => self.stack.push(not(a, &token)?),
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            },
+
             TokenType::Question => {
                 let three = self.variable_check_pop();
-                let two = self.variable_check_pop();
+                // The variable name is used as is, like in assignment
+                let two = self.stack.pop();
                 let one = self.variable_check_pop();
 
-                match three {
-                    Some(a) => {
-                        if a.value == "1" {
-                            self.assign_value(one, two, token)
-                        } else {
-                            // Don't assign
+                match (one, two, three) {
+                    (Some(a), Some(b), Some(condition)) => {
+                        // True is the same as with `bool`, so any number that isn't 0
+                        if truth(&condition)? {
+                            self.assign_value(Some(a), Some(b), token)?;
                         }
                     }
-                    None => stack_empty_error(),
+                    _ => return Err(ComponentError::StackEmpty { operation: token }),
                 }
             }
 
@@ -361,29 +933,64 @@ impl Parser for ParserState {
                 }
             }
 
+            TokenType::DupKeyword
+            | TokenType::DropKeyword
+            | TokenType::SwapKeyword
+            | TokenType::OverKeyword
+            | TokenType::RotKeyword
+            | TokenType::PickKeyword
+            | TokenType::RollKeyword
+            | TokenType::ClearKeyword
+            | TokenType::DepthKeyword => self.stack_word(token)?,
+
             TokenType::VarsKeyword => {
                 for var in &self.local_memory {
                     println!("{:?}", var);
                 }
             }
 
+            TokenType::Identifier if builtin(&token.value).is_some() => {
+                let builtin = builtin(&token.value).expect("Checked by the match guard");
+
+                let out = match builtin.call {
+                    BuiltinCall::Unary(f) => match self.variable_check_pop() {
+                        Some(a) => f(a, &token)?,
+                        None => return Err(ComponentError::StackEmpty { operation: token }),
+                    },
+                    BuiltinCall::Binary(f) => {
+                        let second = self.variable_check_pop();
+                        let first = self.variable_check_pop();
+
+                        match (first, second) {
+                            (Some(a), Some(b)) => f(a, b, &token)?,
+                            _ => return Err(ComponentError::StackEmpty { operation: token }),
+                        }
+                    }
+                };
+
+                self.stack.push(out);
+            }
+
             TokenType::Identifier => {
                 let var = self.local_memory.get(&token.value);
                 let func = self.function_memory.get(&token.value);
 
                 match (var, func) {
                     // Push the value the variable is associated with
-                    (Some(tok), None) => {
-                        if self.stack.len() == 0 {
-                            self.stack.push(tok.clone())
+                    (Some(var), None) => {
+                        if self.stack.is_empty() {
+                            let mut var = var.clone();
+                            var.span = token.span;
+                            self.stack.push(var);
                         } else {
-                            self.stack.push(token);
+                            self.stack.push(Value::identifier(&token.value, token.span));
                         }
                     }
                     // Push the token of type Identifier as an Identifier to the stack
-                    (None, None) => self.stack.push(token),
+                    (None, None) => self.stack.push(Value::identifier(&token.value, token.span)),
                     (None, Some(f)) => {
-                        self.token_stack.append(&mut f.clone());
+                        let body = respanned(f, &token);
+                        self.run_next(body);
                     }
                     (_, _) => {
                         unreachable!();
@@ -395,214 +1002,385 @@ impl Parser for ParserState {
                 let first = self.variable_check_pop();
 
                 if let Some(a) = first {
-                    let a_float_res = a.value.parse::<f64>();
-                    match a_float_res {
-                        Ok(a_val) => self.stack.push(Token {
-                            token_type: TokenType::NumericIntLiteral,
-                            value: ((a_val as f64).sqrt()).to_string(),
-                        }),
-                        Err(_) => wrong_type_error_first(a.value, token.value),
-                    }
+                    self.stack.push(sqrt(a, &token)?);
                 } else {
-                    stack_empty_error();
+                    return Err(ComponentError::StackEmpty { operation: token });
+                }
+            }
+
+            // Unary minus, since `-` on its own is always subtraction
+            TokenType::NegKeyword => {
+                let first = self.variable_check_pop();
+
+                match first {
+                    Some(a) => {
+                        let span = a.span.to(token.span);
+                        let unit = a.unit.clone();
+
+                        let out = match a.value {
+                            TokenValue::IntValue(v) => Value::big_int(-BigInt::from(v), span),
+                            TokenValue::BigIntValue(v) => Value::big_int(-v, span),
+                            TokenValue::DecValue(v) => Value::dec(-v, span),
+                            TokenValue::RatioValue(v) => match v.checked_neg() {
+                                Some(n) => Value::ratio(n, span),
+                                None => Value::dec(-v.to_f64(), span),
+                            },
+                            TokenValue::ComplexValue(v) => Value::complex(-v, span),
+                            _ => {
+                                return Err(ComponentError::WrongType {
+                                    values: vec![a],
+                                    expected: numeric_types(),
+                                })
+                            }
+                        };
+
+                        self.stack.push(out.with_unit(unit));
+                    }
+                    None => return Err(ComponentError::StackEmpty { operation: token }),
+                }
+            }
+
+            TokenType::UnitLiteral => match self.variable_check_pop() {
+                Some(a) => self.stack.push(attach(a, &token)?),
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            },
+
+            // `to` reads the unit after it, so `5 {km} to {m}` converts instead of giving km*m
+            TokenType::ToKeyword => {
+                let unit = match self.token_stack.get(self.token_index + 1) {
+                    Some(next) if next.token_type == TokenType::UnitLiteral => next.clone(),
+                    _ => return Err(ComponentError::UnknownUnit { unit: token }),
+                };
+
+                match self.variable_check_pop() {
+                    Some(a) => {
+                        self.stack.push(convert(a, &unit)?);
+                        self.token_index += 1;
+                    }
+                    None => return Err(ComponentError::StackEmpty { operation: token }),
+                }
+            }
+
+            TokenType::LeftParen => {
+                self.paren_stack.push((self.stack.len(), token));
+            }
+
+            TokenType::LeftBracket => self.quotation(token)?,
+
+            // Any `]` with a `[` before it is read along with the quotation
+            TokenType::RightBracket => {
+                return Err(ComponentError::UnbalancedParentheses { paren: token })
+            }
+
+            TokenType::IfKeyword | TokenType::IfElseKeyword | TokenType::CallKeyword => {
+                self.control_word(token)?
+            }
+
+            TokenType::TimesKeyword | TokenType::WhileKeyword | TokenType::ForKeyword => {
+                self.loop_word(token)?
+            }
+
+            // Everything pushed since the matching `(` becomes a vector, or a matrix if it is all
+            // vectors
+            TokenType::RightParen => match self.paren_stack.pop() {
+                Some((depth, open)) => {
+                    let items: Vec<Value> = self
+                        .stack
+                        .split_off(depth.min(self.stack.len()))
+                        .into_iter()
+                        .map(|item| self.resolve_variable(item))
+                        .collect();
+                    let span = open.span.to(token.span);
+
+                    // Every component has to have the same unit, which the vector then has
+                    let unit = items
+                        .first()
+                        .map(|item| item.unit.clone())
+                        .unwrap_or_default();
+                    if items.iter().any(|item| item.unit != unit) {
+                        return Err(ComponentError::DimensionMismatch {
+                            values: items,
+                            expected: None,
+                        });
+                    }
+
+                    let is_matrix = !items.is_empty()
+                        && items
+                            .iter()
+                            .all(|item| item.value_type == TokenType::VectorLiteral);
+
+                    if is_matrix {
+                        if items.iter().any(|row| row.size() != items[0].size()) {
+                            return Err(ComponentError::SizeMismatch {
+                                values: items,
+                                expected: None,
+                            });
+                        }
+
+                        let rows = items
+                            .into_iter()
+                            .filter_map(|row| match row.value {
+                                TokenValue::VecValue(r) => Some(r),
+                                _ => None,
+                            })
+                            .collect();
+                        self.stack.push(Value::matrix(rows, span).with_unit(unit));
+                    } else {
+                        let (components, wrong): (Vec<Value>, Vec<Value>) =
+                            items.into_iter().partition(|item| item.number().is_some());
+
+                        if !wrong.is_empty() {
+                            return Err(ComponentError::WrongType {
+                                values: wrong,
+                                expected: numeric_types(),
+                            });
+                        }
+
+                        let components = components.into_iter().map(|c| c.value).collect();
+                        self.stack
+                            .push(Value::vector(components, span).with_unit(unit));
+                    }
+                }
+                None => return Err(ComponentError::UnbalancedParentheses { paren: token }),
+            },
+
+            TokenType::DotKeyword
+            | TokenType::CrossKeyword
+            | TokenType::GetKeyword
+            | TokenType::SolveKeyword
+            | TokenType::RoundToKeyword => {
+                let second = self.variable_check_pop();
+                let first = self.variable_check_pop();
+
+                match (first, second) {
+                    (Some(a), Some(b)) => {
+                        let out = match token.token_type {
+                            TokenType::DotKeyword => dot(a, b, &token)?,
+                            TokenType::CrossKeyword => cross(a, b, &token)?,
+                            TokenType::SolveKeyword => solve(a, b, &token)?,
+                            TokenType::RoundToKeyword => round_to(a, b, self.round_mode, &token)?,
+                            _ => get(a, b, &token)?,
+                        };
+                        self.stack.push(out);
+                    }
+                    _ => return Err(ComponentError::StackEmpty { operation: token }),
                 }
             }
 
+            // Strings are printed without their quotes, so a script can label what it prints
+            TokenType::PrintKeyword => match self.variable_check_pop() {
+                Some(a) => println!("{}", a),
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            },
+
+            TokenType::NormKeyword
+            | TokenType::LenKeyword
+            | TokenType::TransposeKeyword
+            | TokenType::DetKeyword
+            | TokenType::InvKeyword
+            | TokenType::IdentityKeyword => match self.variable_check_pop() {
+                Some(a) => {
+                    let out = match token.token_type {
+                        TokenType::NormKeyword => norm(a, &token)?,
+                        TokenType::LenKeyword => len(a, &token)?,
+                        TokenType::TransposeKeyword => transpose(a, &token)?,
+                        TokenType::DetKeyword => det(a, &token)?,
+                        TokenType::InvKeyword => inv(a, &token)?,
+                        _ => identity(a, &token)?,
+                    };
+                    self.stack.push(out);
+                }
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            },
+
+            TokenType::RoundKeyword
+            | TokenType::FloorKeyword
+            | TokenType::CeilKeyword
+            | TokenType::TruncKeyword => match self.variable_check_pop() {
+                Some(a) => {
+                    let rounding = match token.token_type {
+                        TokenType::FloorKeyword => Rounding::Floor,
+                        TokenType::CeilKeyword => Rounding::Ceil,
+                        TokenType::TruncKeyword => Rounding::Trunc,
+                        _ => Rounding::Nearest(self.round_mode),
+                    };
+                    self.stack.push(round(a, rounding, &token)?);
+                }
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            },
+
+            TokenType::ReKeyword
+            | TokenType::ImKeyword
+            | TokenType::ConjKeyword
+            | TokenType::ArgKeyword => match self.variable_check_pop() {
+                Some(a) => {
+                    let out = match token.token_type {
+                        TokenType::ReKeyword => re(a, &token)?,
+                        TokenType::ImKeyword => im(a, &token)?,
+                        TokenType::ConjKeyword => conj(a, &token)?,
+                        _ => arg(a, &token)?,
+                    };
+                    self.stack.push(out);
+                }
+                None => return Err(ComponentError::StackEmpty { operation: token }),
+            },
+
             // Create variables
             TokenType::Assignment => {
                 // Use raw stack.pop here
                 let second = self.stack.pop();
                 let first = self.stack.pop();
 
-                self.assign_value(first, second, token);
-            }
-
-            TokenType::RoundKeyword => {
-                self.convert_to_int(token);
+                self.assign_value(first, second, token)?;
             }
 
             TokenType::TypeIntKeyword => {
-                self.convert_to_int(token);
+                self.convert_to_int(token)?;
             }
 
             TokenType::TypeDecKeyword => {
                 let first = self.variable_check_pop();
 
                 match first {
-                    Some(a) => match a.token_type {
-                        TokenType::NumericIntLiteral
-                        | TokenType::NumericDecLiteral
-                        | TokenType::BoolLiteral => {
-                            let a_float_res = a.value.parse::<f64>();
-                            match a_float_res {
-                                Ok(a_val) => self.stack.push(Token {
-                                    token_type: TokenType::NumericDecLiteral,
-                                    value: (a_val as f64).to_string(),
-                                }),
-                                Err(_) => wrong_type_error_first(a.value, token.value),
-                            }
+                    Some(a) => match a.as_f64() {
+                        Some(a_val) => {
+                            let out = Value::dec(a_val, a.span.to(token.span));
+                            self.stack.push(out.with_unit(a.unit))
+                        }
+                        None => {
+                            return Err(ComponentError::InvalidTypeCast {
+                                value: Box::new(a),
+                                cast: Box::new(token),
+                                cast_to: TokenType::NumericDecLiteral,
+                            })
                         }
-                        _ => invalid_type_cast_error(String::from("NumericIntLiteral"), a, token),
                     },
-                    None => stack_empty_error(),
+                    None => return Err(ComponentError::StackEmpty { operation: token }),
                 }
             }
 
-            TokenType::TypeBoolKeyword => {
+            // Decimals become the closest simple fraction, so `1 3 / ratio` is `1/3`
+            TokenType::TypeRatioKeyword => {
                 let first = self.variable_check_pop();
 
-                self.convert_to_bool(first, token);
+                match first {
+                    Some(a) => {
+                        let ratio = match a.value {
+                            TokenValue::IntValue(v) => Some(Ratio::from_int(v)),
+                            TokenValue::DecValue(v) => Ratio::from_f64(v),
+                            TokenValue::RatioValue(v) => Some(v),
+                            TokenValue::BoolValue(b) => Some(Ratio::from_int(b as i64)),
+                            _ => None,
+                        };
+
+                        match ratio {
+                            Some(r) => self.stack.push(Value::ratio(r, a.span.to(token.span))),
+                            None => {
+                                return Err(ComponentError::InvalidTypeCast {
+                                    value: Box::new(a),
+                                    cast: Box::new(token),
+                                    cast_to: TokenType::RatioLiteral,
+                                })
+                            }
+                        }
+                    }
+                    None => return Err(ComponentError::StackEmpty { operation: token }),
+                }
             }
 
-            // Simple two argument operations/functions
-            TokenType::Addition
-            | TokenType::Multiplication
-            | TokenType::Subtraction
-            | TokenType::Division
-            | TokenType::Carrot => {
-                let second = self.variable_check_pop();
+            // `complex` keeps the number as the real part and `imaginary` makes it the imaginary
+            // part, so `2 3 imaginary +` is `2+3i`
+            TokenType::TypeComplexKeyword | TokenType::TypeImaginaryKeyword => {
                 let first = self.variable_check_pop();
 
-                // Check that both items poped from the stack actually exist
-                // i.e. there are enough items on the stack
-                match (first, second) {
-                    // Both items exist on the stack
-                    (Some(a), Some(b)) => {
-                        // Check the type of the two items poped from the stack
-                        match (a.token_type, b.token_type) {
-                            // Both token are NumericIntLiteral or NumericDecLiteral
-                            (m, n)
-                                if (m == TokenType::NumericDecLiteral
-                                    || m == TokenType::NumericIntLiteral)
-                                    && (n == TokenType::NumericDecLiteral
-                                        || n == TokenType::NumericIntLiteral) =>
-                            {
-                                let a_float_res = a.value.parse::<f64>();
-                                let b_float_res = b.value.parse::<f64>();
-
-                                // Parse should keep (NumericIntLiteral NumericIntLiteral Operation) as an NumericIntLiteral
-                                // if it's still a whole number and both inputs are ints
-                                let keep_int = m == TokenType::NumericIntLiteral
-                                    && n == TokenType::NumericIntLiteral;
-
-                                // Check that both numbers parsed correctly
-                                match (a_float_res, b_float_res) {
-                                    // Both values parsed correctly
-                                    (Ok(a_float), Ok(b_float)) => match token.token_type {
-                                        TokenType::Addition => {
-                                            let v = a_float + b_float;
-                                            let t_type = if v.fract() == 0.0 && keep_int {
-                                                TokenType::NumericIntLiteral
-                                            } else {
-                                                TokenType::NumericDecLiteral
-                                            };
-                                            self.stack.push(Token {
-                                                token_type: t_type,
-                                                value: v.to_string(),
-                                            });
-                                            self.asm_add();
-                                        }
-                                        TokenType::Multiplication => {
-                                            let v = a_float * b_float;
-                                            let t_type = if v.fract() == 0.0 && keep_int {
-                                                TokenType::NumericIntLiteral
-                                            } else {
-                                                TokenType::NumericDecLiteral
-                                            };
-                                            self.stack.push(Token {
-                                                token_type: t_type,
-                                                value: v.to_string(),
-                                            });
-                                        }
-                                        TokenType::Subtraction => {
-                                            let v = a_float - b_float;
-                                            let t_type = if v.fract() == 0.0 && keep_int {
-                                                TokenType::NumericIntLiteral
-                                            } else {
-                                                TokenType::NumericDecLiteral
-                                            };
-                                            self.stack.push(Token {
-                                                token_type: t_type,
-                                                value: v.to_string(),
-                                            });
-                                            self.asm_sub();
-                                        }
-                                        TokenType::Division => {
-                                            let v = a_float / b_float;
-                                            let t_type = if v.fract() == 0.0 && keep_int {
-                                                TokenType::NumericIntLiteral
-                                            } else {
-                                                TokenType::NumericDecLiteral
-                                            };
-                                            self.stack.push(Token {
-                                                token_type: t_type,
-                                                value: v.to_string(),
-                                            });
-                                        }
-                                        TokenType::Carrot => {
-                                            let v = a_float.powf(b_float);
-                                            let t_type = if v.fract() == 0.0 && keep_int {
-                                                TokenType::NumericIntLiteral
-                                            } else {
-                                                TokenType::NumericDecLiteral
-                                            };
-                                            self.stack.push(Token {
-                                                token_type: t_type,
-                                                value: v.to_string(),
-                                            });
-                                        }
-                                        _ => {
-                                            unreachable!();
-                                        }
-                                    },
-
-                                    // Give errors if values did not parse correctly
-                                    (Err(_), Ok(_)) => wrong_type_error_first(b.value, token.value),
-                                    (Ok(_), Err(_)) => {
-                                        wrong_type_error_second(a.value, b.value, token.value)
-                                    }
-                                    (Err(_), Err(_)) => {
-                                        wrong_type_error_both(a.value, b.value, token.value)
-                                    }
-                                }
-                            }
+                match first {
+                    Some(a) => {
+                        let complex = match a.value {
+                            TokenValue::BoolValue(b) => Some(Complex::new(b as i64 as f64, 0.0)),
+                            _ => complex_of(&a.value),
+                        };
 
-                            // Give errors if values are not NumericIntLiteral or NumericDecLiteral
-                            (_, TokenType::NumericIntLiteral) => {
-                                wrong_type_error_first(b.value, token.value)
+                        match complex {
+                            Some(c) => {
+                                let c = match token.token_type {
+                                    TokenType::TypeImaginaryKeyword => c * Complex::new(0.0, 1.0),
+                                    _ => c,
+                                };
+                                self.stack.push(Value::complex(c, a.span.to(token.span)));
                             }
-
-                            (TokenType::NumericIntLiteral, _) => {
-                                wrong_type_error_first(a.value, token.value)
+                            None => {
+                                return Err(ComponentError::InvalidTypeCast {
+                                    value: Box::new(a),
+                                    cast: Box::new(token),
+                                    cast_to: TokenType::ComplexLiteral,
+                                })
                             }
-                            (_, _) => wrong_type_error_both(a.value, b.value, token.value),
                         }
                     }
-                    _ => stack_empty_error(),
+                    None => return Err(ComponentError::StackEmpty { operation: token }),
                 }
             }
 
-            _ => {
-                println!(
-                    "{} Operation Not Implemented [E5]",
-                    color!(Color::RED, bold!("Error:").as_str()).as_str()
-                );
-                println!(
-                    "{}",
-                    color!(Color::RED, bold!(token.value.as_str()).as_str()),
-                );
-                println!(
-                    "{} operation is not implemented",
-                    color!(
-                        Color::RED,
-                        bold!(&(0..token.value.len()).map(|_| "^").collect::<String>()).as_str()
-                    ),
-                );
+            TokenType::TypeBoolKeyword => {
+                let first = self.variable_check_pop();
+
+                self.convert_to_bool(first, token)?;
+            }
+
+            // Simple two argument operations/functions
+            TokenType::Addition
+            | TokenType::Multiplication
+            | TokenType::Subtraction
+            | TokenType::Division
+            | TokenType::IntDivision
+            | TokenType::Percent
+            | TokenType::Carrot => {
+                let second = self.variable_check_pop();
+                let first = self.variable_check_pop();
+
+                // Check that both items poped from the stack actually exist
+                // i.e. there are enough items on the stack
+                match (first, second) {
+                    (Some(a), Some(b)) => {
+                        let out = arithmetic(a, b, &token)?;
+
+                        match token.token_type {
+                            TokenType::Addition => self.asm_add(),
+                            TokenType::Subtraction => self.asm_sub(),
+                            _ => {}
+                        }
+
+                        self.stack.push(out);
+                    }
+                    _ => return Err(ComponentError::StackEmpty { operation: token }),
+                }
             }
+
+            _ => return Err(ComponentError::OperationNotImplemented { operation: token }),
+        }
+
+        Ok(())
+    }
+
+    fn parse(&mut self, tokens: Vec<Token>) -> Result<Value, ComponentError> {
+        // Each call is all or nothing, so a line that errors part way through can't leave
+        // half of its changes behind
+        let stack = self.stack.clone();
+        let local_memory = self.local_memory.clone();
+        let function_memory = self.function_memory.clone();
+
+        let result = self.run_tokens(tokens);
+
+        if result.is_err() {
+            self.stack = stack;
+            self.local_memory = local_memory;
+            self.function_memory = function_memory;
         }
+
+        result
     }
 
-    fn parse(&mut self, tokens: Vec<Token>) -> Token {
+    fn run_tokens(&mut self, tokens: Vec<Token>) -> Result<Value, ComponentError> {
         self.reset_asm();
 
         if self.verbose {
@@ -615,8 +1393,12 @@ impl Parser for ParserState {
             );
         }
 
+        // Anything left over from a line that stopped with an error is thrown away
         self.function_mode = false;
-        self.token_stack.append(&mut tokens.clone());
+        self.function_stack.clear();
+        self.paren_stack.clear();
+        self.token_stack = tokens;
+        self.token_index = 0;
 
         // Parse postfix notation
         while self.token_index < self.token_stack.len() {
@@ -645,62 +1427,44 @@ impl Parser for ParserState {
                     TokenType::Function => {
                         self.function_mode = false;
 
-                        let func_name = self.function_stack.pop();
-
-                        if let Some(name) = func_name {
-                            match self.function_memory.get(&name.value) {
-                                Some(_) => {
-                                    println!(
-                                        "{} Function Already Exists [E6]",
-                                        color!(Color::RED, bold!("Error:").as_str()).as_str()
-                                    );
-                                    println!(
-                                        "{}",
-                                        color!(Color::RED, bold!(name.value.as_str()).as_str()),
-                                    );
-                                    println!(
-                                        "{} function with the same name already exists",
-                                        color!(
-                                            Color::RED,
-                                            bold!(&(0..name.value.len())
-                                                .map(|_| "^")
-                                                .collect::<String>())
-                                            .as_str()
-                                        ),
-                                    );
-                                }
-                                None => {
-                                    self.function_memory
-                                        .insert(name.value, self.function_stack.clone());
-                                    // Clear the function off the stack
-                                    self.function_stack = Vec::<Token>::new();
-
-                                    if self.verbose {
-                                        println!(
-                                            "{}",
-                                            color!(
-                                                Color::BLACK,
-                                                format!(
-                                                    "Function memory: {:?}",
-                                                    self.function_memory
-                                                )
-                                                .as_str()
-                                            )
-                                        );
-                                    }
-                                }
-                            }
+                        let name = match self.function_stack.pop() {
+                            Some(name) => name,
+                            None => return Err(ComponentError::StackEmpty { operation: token }),
+                        };
+
+                        if self.function_memory.contains_key(&name.value)
+                            || builtin(&name.value).is_some()
+                        {
+                            return Err(ComponentError::FunctionAlreadyExists { name });
+                        }
+
+                        self.function_memory
+                            .insert(name.value, self.function_stack.clone());
+                        // Clear the function off the stack
+                        self.function_stack = Vec::<Token>::new();
+
+                        if self.verbose {
+                            println!(
+                                "{}",
+                                color!(
+                                    Color::BLACK,
+                                    format!("Function memory: {:?}", self.function_memory).as_str()
+                                )
+                            );
                         }
+
                         self.token_index += 1;
 
                         continue;
                     }
+                    // A function body can be spread over several lines
+                    TokenType::Newline => {}
                     _ => {
                         self.function_stack.push(token.clone());
                     }
                 }
             } else {
-                self.match_token_type(token);
+                self.match_token_type(token)?;
             }
 
             self.token_index += 1;
@@ -716,16 +1480,13 @@ impl Parser for ParserState {
             );
         }
 
+        if let Some((_, open)) = self.paren_stack.pop() {
+            return Err(ComponentError::UnbalancedParentheses { paren: open });
+        }
+
         match self.stack.pop() {
-            Some(a) => {
-                return a;
-            }
-            None => {
-                return Token {
-                    token_type: TokenType::NoType,
-                    value: "".to_string(),
-                }
-            }
+            Some(a) => Ok(a),
+            None => Ok(Value::none()),
         }
     }
 }
@@ -736,47 +1497,56 @@ pub fn create_parser(verbose: bool) -> ParserState {
 
     local_memory.insert(
         "e".to_string(),
-        Token {
-            token_type: TokenType::NumericDecLiteral,
-            value: std::f64::consts::E.to_string(),
-        },
+        Value::dec(std::f64::consts::E, Span::default()),
     );
 
     local_memory.insert(
         "pi".to_string(),
-        Token {
-            token_type: TokenType::NumericDecLiteral,
-            value: std::f64::consts::PI.to_string(),
-        },
+        Value::dec(std::f64::consts::PI, Span::default()),
     );
 
-    local_memory.insert(
-        "C".to_string(),
-        Token {
-            token_type: TokenType::NumericIntLiteral,
-            value: "299792458".to_string(),
-        },
-    );
+    local_memory.insert("C".to_string(), Value::int(299792458, Span::default()));
 
     ParserState {
         function_mode: false,
         verbose,
         function_memory,
         local_memory,
-        stack: Vec::<Token>::new(),
+        stack: Vec::<Value>::new(),
         token_stack: Vec::<Token>::new(),
         function_stack: Vec::<Token>::new(),
+        paren_stack: Vec::new(),
         token_index: 0,
         assembly: Vec::<String>::new(),
         assembly_arch: AssemblyArchitecture::RISCV,
         temp_reg_index: 0,
         temp_reg_index_init: 0,
+        round_mode: RoundMode::default(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::{Lex, Lexer};
+    use crate::unit::Unit;
+
+    fn lex_line(line: &str) -> Vec<Token> {
+        let mut lex = Lexer::new(vec![line.to_string()]);
+        let mut tokens = Vec::<Token>::new();
+
+        loop {
+            let a = lex.next();
+
+            if a.token_type == TokenType::EndToken {
+                break;
+            }
+
+            tokens.push(a);
+        }
+
+        tokens
+    }
 
     #[test]
     fn parse_test_1() {
@@ -784,27 +1554,24 @@ mod tests {
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "1".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "1".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Addition,
                 value: "+".to_string(),
+                span: Span::default(),
             },
         ];
 
         let mut p = create_parser(true);
-        let out1 = p.parse(input1);
+        let out1 = p.parse(input1).unwrap();
 
-        assert_eq!(
-            out1,
-            Token {
-                token_type: TokenType::NumericIntLiteral,
-                value: "2".to_string(),
-            },
-        );
+        assert_eq!(out1, Value::int(2, Span::default()),);
     }
 
     #[test]
@@ -813,27 +1580,24 @@ mod tests {
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "3".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "2".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Multiplication,
                 value: "*".to_string(),
+                span: Span::default(),
             },
         ];
 
         let mut p = create_parser(true);
-        let out2 = p.parse(input2);
+        let out2 = p.parse(input2).unwrap();
 
-        assert_eq!(
-            out2,
-            Token {
-                token_type: TokenType::NumericIntLiteral,
-                value: "6".to_string(),
-            },
-        );
+        assert_eq!(out2, Value::int(6, Span::default()),);
     }
 
     #[test]
@@ -842,35 +1606,34 @@ mod tests {
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "3".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "2".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Addition,
                 value: "+".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "10".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Multiplication,
                 value: "*".to_string(),
+                span: Span::default(),
             },
         ];
 
         let mut p = create_parser(true);
-        let out3 = p.parse(input3);
+        let out3 = p.parse(input3).unwrap();
 
-        assert_eq!(
-            out3,
-            Token {
-                token_type: TokenType::NumericIntLiteral,
-                value: "50".to_string(),
-            },
-        );
+        assert_eq!(out3, Value::int(50, Span::default()),);
     }
 
     #[test]
@@ -879,118 +1642,1535 @@ mod tests {
             Token {
                 token_type: TokenType::Greater,
                 value: ">".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Identifier,
                 value: "t".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "1".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Identifier,
                 value: "v".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "2".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Carrot,
                 value: "^".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Identifier,
                 value: "C".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "2".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Carrot,
                 value: "^".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Division,
                 value: "/".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Subtraction,
                 value: "-".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::TypeSqrtKeyword,
                 value: "sqrt".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Division,
                 value: "/".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Identifier,
                 value: "s".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Function,
                 value: "fn".to_string(),
+                span: Span::default(),
             },
         ];
 
         let mut p = create_parser(true);
-        let out4 = p.parse(input4);
+        let out4 = p.parse(input4).unwrap();
 
-        assert_eq!(
-            out4,
-            Token {
-                token_type: TokenType::NoType,
-                value: "".to_string(),
-            },
-        );
+        assert_eq!(out4, Value::none(),);
 
         let input5 = vec![
             // Set var v
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "300040".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Identifier,
                 value: "v".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Assignment,
                 value: "=".to_string(),
+                span: Span::default(),
             },
             // Set var t
             Token {
                 token_type: TokenType::NumericIntLiteral,
                 value: "30".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Identifier,
                 value: "t".to_string(),
+                span: Span::default(),
             },
             Token {
                 token_type: TokenType::Assignment,
                 value: "=".to_string(),
+                span: Span::default(),
             },
             // Call s
             Token {
                 token_type: TokenType::Identifier,
                 value: "s".to_string(),
+                span: Span::default(),
             },
         ];
 
-        let out5 = p.parse(input5);
+        let out5 = p.parse(input5).unwrap();
+
+        assert_eq!(out5, Value::dec(30.00001502479285, Span::default()),);
+    }
+
+    #[test]
+    fn parse_negative_literal_test() {
+        let mut p = create_parser(false);
 
+        // `-3` is a literal but the trailing `-` is subtraction
         assert_eq!(
-            out5,
-            Token {
-                token_type: TokenType::NumericDecLiteral,
-                value: "30.00001502479285".to_string(),
-            },
+            p.parse(lex_line("5 -3 -")).unwrap(),
+            Value::int(8, Span::default()),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("-0.5 2 *")).unwrap(),
+            Value::dec(-1.0, Span::default()),
+        );
+    }
+
+    #[test]
+    fn parse_sci_and_radix_test() {
+        let mut p = create_parser(false);
+
+        // Very big and very small decimals are written in scientific notation
+        let out = p.parse(lex_line("6.022e23")).unwrap();
+        assert_eq!(out.to_string(), "6.022e23");
+
+        let out = p.parse(lex_line("1.6E-19 2 *")).unwrap();
+        assert_eq!(out.to_string(), "3.2e-19");
+
+        let out = p.parse(lex_line("2e3")).unwrap();
+        assert_eq!(out.to_string(), "2000");
+
+        // Radix literals can be as big as any other int
+        let out = p.parse(lex_line("0xFFFFFFFFFFFFFFFFFF")).unwrap();
+        assert_eq!(out.to_string(), "4722366482869645213695");
+    }
+
+    #[test]
+    fn parse_neg_test() {
+        let mut p = create_parser(false);
+
+        assert_eq!(
+            p.parse(lex_line("5 neg")).unwrap(),
+            Value::int(-5, Span::default()),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("-2.5 neg")).unwrap(),
+            Value::dec(2.5, Span::default()),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("2 3 + neg 1 -")).unwrap(),
+            Value::int(-6, Span::default()),
+        );
+    }
+
+    #[test]
+    fn parse_multi_line_test() {
+        let lines = vec![
+            "> t 1 v 2 ^ C 2 ^ /".to_string(),
+            "  - sqrt / s fn".to_string(),
+            "300040 v =".to_string(),
+            "30 t =".to_string(),
+            "s".to_string(),
+        ];
+
+        let mut lex = Lexer::new(lines);
+        lex.set_newlines(true);
+
+        let mut tokens = Vec::<Token>::new();
+        loop {
+            let a = lex.next();
+
+            if a.token_type == TokenType::EndToken {
+                break;
+            }
+
+            tokens.push(a);
+        }
+
+        let mut p = create_parser(false);
+
+        assert_eq!(
+            p.parse(tokens).unwrap(),
+            Value::dec(30.00001502479285, Span::default()),
+        );
+    }
+
+    #[test]
+    fn split_statements_test() {
+        let lines = vec![
+            "1 1 +".to_string(),
+            "> 2 *".to_string(),
+            "  twice fn".to_string(),
+            "(1".to_string(),
+            " 2) 3 [1".to_string(),
+            "+] call".to_string(),
+            "".to_string(),
+            "4 twice 1 2 >".to_string(),
+        ];
+
+        let mut lex = Lexer::new(lines);
+        lex.set_newlines(true);
+
+        let mut tokens = Vec::<Token>::new();
+        loop {
+            let a = lex.next();
+
+            if a.token_type == TokenType::EndToken {
+                break;
+            }
+
+            tokens.push(a);
+        }
+
+        // Functions, parentheses and brackets keep a statement going onto the next line
+        let statements = split_statements(&tokens);
+        let lines: Vec<(usize, usize)> = statements
+            .iter()
+            .map(|s| (s[0].span.line, s[s.len() - 1].span.line))
+            .collect();
+        assert_eq!(lines, vec![(0, 0), (1, 2), (3, 5), (7, 7)]);
+
+        // Each statement gives its own result
+        let mut p = create_parser(false);
+        let results: Vec<Value> = statements
+            .into_iter()
+            .map(|s| p.parse(s).unwrap())
+            .collect();
+        assert_eq!(results[0], Value::int(2, Span::default()));
+        assert_eq!(results[1], Value::none());
+        assert_eq!(results[2], Value::int(4, Span::default()));
+        assert_eq!(results[3], Value::boolean(false, Span::default()));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let mut p = create_parser(false);
+
+        match p.parse(lex_line("1 +")) {
+            Err(ComponentError::StackEmpty { operation }) => {
+                assert_eq!(operation.value, "+");
+                assert_eq!(operation.span.start, 2);
+            }
+            other => panic!("expected StackEmpty, got {:?}", other),
+        }
+
+        match p.parse(lex_line("1 a 2 +")) {
+            Err(ComponentError::WrongType { values, expected }) => {
+                assert_eq!(values.len(), 1);
+                assert_eq!(values[0], Value::identifier("a", Span::default()));
+                assert!(expected.contains(&TokenType::NumericIntLiteral));
+            }
+            other => panic!("expected WrongType, got {:?}", other),
+        }
+
+        match p.parse(lex_line("a int")) {
+            Err(ComponentError::InvalidTypeCast { value, cast_to, .. }) => {
+                assert_eq!(value.value_type, TokenType::Identifier);
+                assert_eq!(cast_to, TokenType::NumericIntLiteral);
+            }
+            other => panic!("expected InvalidTypeCast, got {:?}", other),
+        }
+
+        match p.parse(lex_line("$")) {
+            Err(ComponentError::OperationNotImplemented { operation }) => {
+                assert_eq!(operation.value, "$");
+            }
+            other => panic!("expected OperationNotImplemented, got {:?}", other),
+        }
+
+        // A literal too big to read points at itself rather than at a missing operation
+        match p.parse(lex_line("1 1e400 +")) {
+            Err(ComponentError::InvalidLiteral { literal }) => {
+                assert_eq!(literal.value, "1e400");
+                assert_eq!(literal.span.start, 2);
+            }
+            other => panic!("expected InvalidLiteral, got {:?}", other),
+        }
+
+        let err = p.parse(lex_line("2e999i")).unwrap_err();
+        assert_eq!(err.code(), "E14");
+
+        // So does a number that is written wrong
+        for line in ["0x", "1e 2 +", "0b12"] {
+            match p.parse(lex_line(line)) {
+                Err(ComponentError::InvalidLiteral { literal }) => {
+                    assert_eq!(literal.span.start, 0);
+                }
+                other => panic!("expected InvalidLiteral, got {:?}", other),
+            }
+        }
+
+        let err = p.parse(lex_line("0x")).unwrap_err();
+        assert_eq!(err.message(), "0x is not written like a number");
+    }
+
+    #[test]
+    fn parse_assignment_error_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("1 a =")).unwrap();
+
+        let err = p.parse(lex_line("4 5 / a =")).unwrap_err();
+        assert_eq!(err.code(), "E1");
+        assert_eq!(
+            err,
+            ComponentError::AssignmentTypeMismatch {
+                value: Box::new(Value::dec(0.8, Span::default())),
+                variable: Box::new(Value::identifier("a", Span::default())),
+                variable_type: TokenType::NumericIntLiteral,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_function_error_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("> + foo fn")).unwrap();
+
+        let err = p.parse(lex_line("> - foo fn")).unwrap_err();
+        assert_eq!(err.code(), "E6");
+
+        // The failed definition doesn't leak into the next function
+        p.parse(lex_line("> - bar fn")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("5 3 bar")).unwrap(),
+            Value::int(2, Span::default()),
+        );
+    }
+
+    #[test]
+    fn parse_rollback_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("7 w =")).unwrap();
+        p.parse(lex_line("> 1 + inc fn")).unwrap();
+        assert_eq!(p.stack.len(), 0);
+
+        // `y` gets assigned and `twice` gets defined before the assignment to `w` fails
+        let err = p
+            .parse(lex_line("8 9 1.5 y = > 2 * twice fn 4.5 w ="))
+            .unwrap_err();
+        assert_eq!(err.code(), "E1");
+
+        assert_eq!(p.stack.len(), 0);
+        assert!(!p.local_memory.contains_key("y"));
+        assert!(!p.function_memory.contains_key("twice"));
+        assert_eq!(
+            p.local_memory.get("w").unwrap().value,
+            TokenValue::IntValue(7)
+        );
+        assert!(p.function_memory.contains_key("inc"));
+
+        // Values popped by an operation that fails are put back
+        p.parse(lex_line("1 2")).unwrap();
+        assert_eq!(p.stack.len(), 1);
+        p.parse(lex_line("true +")).unwrap_err();
+        assert_eq!(p.stack, vec![Value::int(1, Span::default())]);
+    }
+
+    #[test]
+    fn parse_int_value_test() {
+        let mut p = create_parser(false);
+
+        // Past 2^53 an f64 can't hold every int, but an i64 can
+        assert_eq!(
+            p.parse(lex_line("9007199254740993 1 +")).unwrap(),
+            Value::int(9007199254740994, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("3 39 ^")).unwrap(),
+            Value::int(4052555153018976267, Span::default()),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("12 4 /")).unwrap(),
+            Value::int(3, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("7 2 /")).unwrap(),
+            Value::dec(3.5, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("2 -1 ^")).unwrap(),
+            Value::dec(0.5, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("16 sqrt")).unwrap(),
+            Value::dec(4.0, Span::default()),
+        );
+
+        // Too big for an i64, so it becomes a big int
+        let big = |v: &str| Value::big_int(v.parse().unwrap(), Span::default());
+        assert_eq!(
+            p.parse(lex_line("9223372036854775807 1 +")).unwrap(),
+            big("9223372036854775808"),
+        );
+        assert_eq!(
+            p.parse(lex_line("2 64 ^")).unwrap(),
+            big("18446744073709551616"),
+        );
+        assert_eq!(
+            p.parse(lex_line("-9223372036854775808 neg")).unwrap(),
+            big("9223372036854775808"),
+        );
+        assert_eq!(
+            p.parse(lex_line("99999999999999999999999 1 +")).unwrap(),
+            big("100000000000000000000000"),
+        );
+        assert_eq!(
+            p.parse(lex_line("C C * C * C *")).unwrap(),
+            big("8077608713062490229263800746151696"),
+        );
+
+        // Big ints that get small again are the same as any other int
+        assert_eq!(
+            p.parse(lex_line("2 64 ^ 2 63 ^ /")).unwrap(),
+            Value::int(2, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("2 64 ^ 1 + 2 64 ^ -")).unwrap(),
+            Value::int(1, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("2 64 ^ 3 /")).unwrap(),
+            Value::dec(18446744073709551616.0 / 3.0, Span::default()),
+        );
+
+        // Ints too big to work out exactly are an error instead of `inf`
+        let err = p.parse(lex_line("2 100000000 ^")).unwrap_err();
+        assert_eq!(err.code(), "E15");
+
+        let err = p.parse(lex_line("-2 100000001 ^")).unwrap_err();
+        assert_eq!(err.code(), "E15");
+
+        let err = p.parse(lex_line("(2 3) 100000000 ^")).unwrap_err();
+        assert_eq!(err.code(), "E15");
+    }
+
+    fn vector(values: Vec<TokenValue>) -> Value {
+        Value::vector(values, Span::default())
+    }
+
+    #[test]
+    fn parse_vector_test() {
+        let mut p = create_parser(false);
+
+        assert_eq!(
+            p.parse(lex_line("(1 2 3) (5 6 7) cross")).unwrap(),
+            vector(vec![
+                TokenValue::IntValue(-4),
+                TokenValue::IntValue(8),
+                TokenValue::IntValue(-4)
+            ]),
+        );
+
+        // `x` is left free to be a variable or a function parameter
+        p.parse(lex_line("(1 0 0) x =")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("x (0 1 0) cross")).unwrap(),
+            vector(vec![
+                TokenValue::IntValue(0),
+                TokenValue::IntValue(0),
+                TokenValue::IntValue(1)
+            ]),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("(1 2 3) (5 6 7) +")).unwrap(),
+            vector(vec![
+                TokenValue::IntValue(6),
+                TokenValue::IntValue(8),
+                TokenValue::IntValue(10)
+            ]),
+        );
+
+        // A number with a vector works on every component
+        assert_eq!(
+            p.parse(lex_line("(1 2 3) 2 *")).unwrap(),
+            vector(vec![
+                TokenValue::IntValue(2),
+                TokenValue::IntValue(4),
+                TokenValue::IntValue(6)
+            ]),
+        );
+        assert_eq!(
+            p.parse(lex_line("1 (2 4) /")).unwrap(),
+            vector(vec![TokenValue::DecValue(0.5), TokenValue::DecValue(0.25)]),
+        );
+
+        // Components can be expressions and variables
+        p.parse(lex_line("(3 1 1 + 2 ^) v =")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("v v dot")).unwrap(),
+            Value::int(25, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("v norm")).unwrap(),
+            Value::dec(5.0, Span::default()),
         );
+        assert_eq!(
+            p.parse(lex_line("v 1 get")).unwrap(),
+            Value::int(4, Span::default()),
+        );
+    }
+
+    #[test]
+    fn parse_vector_error_test() {
+        let mut p = create_parser(false);
+
+        let err = p.parse(lex_line("(1 2) (1 2 3) +")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+
+        let err = p.parse(lex_line("(1 2) (3 4) cross")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+
+        let err = p.parse(lex_line("(1 2) 2 get")).unwrap_err();
+        assert_eq!(err.code(), "E8");
+
+        let err = p.parse(lex_line("(1 2")).unwrap_err();
+        assert_eq!(err.code(), "E9");
+
+        let err = p.parse(lex_line("1 2)")).unwrap_err();
+        assert_eq!(err.code(), "E9");
+
+        let err = p.parse(lex_line("(1 a)")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("3 4 dot")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        assert_eq!(p.stack.len(), 0);
+    }
+
+    fn matrix(rows: Vec<Vec<i64>>) -> Value {
+        let rows = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(TokenValue::IntValue).collect())
+            .collect();
+        Value::matrix(rows, Span::default())
+    }
+
+    #[test]
+    fn parse_matrix_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("((1 2) (3 4)) m =")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("m")).unwrap(),
+            matrix(vec![vec![1, 2], vec![3, 4]]),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("m transpose")).unwrap(),
+            matrix(vec![vec![1, 3], vec![2, 4]]),
+        );
+        assert_eq!(
+            p.parse(lex_line("m m *")).unwrap(),
+            matrix(vec![vec![7, 10], vec![15, 22]]),
+        );
+        assert_eq!(
+            p.parse(lex_line("m 2 identity +")).unwrap(),
+            matrix(vec![vec![2, 2], vec![3, 5]]),
+        );
+        assert_eq!(
+            p.parse(lex_line("m 10 *")).unwrap(),
+            matrix(vec![vec![10, 20], vec![30, 40]]),
+        );
+
+        // A vector on the right is a column and on the left is a row
+        assert_eq!(
+            p.parse(lex_line("m (1 1) *")).unwrap(),
+            vector(vec![TokenValue::IntValue(3), TokenValue::IntValue(7)]),
+        );
+        assert_eq!(
+            p.parse(lex_line("(1 1) m *")).unwrap(),
+            vector(vec![TokenValue::IntValue(4), TokenValue::IntValue(6)]),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("m det")).unwrap(),
+            Value::int(-2, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("((2 0 1) (1 3 2) (1 1 2)) det")).unwrap(),
+            Value::int(6, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("((0 1) (1 0)) det")).unwrap(),
+            Value::int(-1, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("((0.5 0) (0 4)) det")).unwrap(),
+            Value::dec(2.0, Span::default()),
+        );
+
+        // Ints and ratios are inverted exactly
+        let half = |n: i64| TokenValue::RatioValue(Ratio::new(n, 2).unwrap());
+        assert_eq!(
+            p.parse(lex_line("m inv")).unwrap(),
+            Value::matrix(
+                vec![
+                    vec![TokenValue::IntValue(-2), TokenValue::IntValue(1)],
+                    vec![half(3), half(-1)],
+                ],
+                Span::default()
+            ),
+        );
+        let out = p.parse(lex_line("m inv m *")).unwrap();
+        assert_eq!(out.to_string(), "((1 0) (0 1))");
+        assert_eq!(
+            p.parse(lex_line("((0.5 1) (0 2)) inv")).unwrap(),
+            Value::matrix(
+                vec![
+                    vec![TokenValue::DecValue(2.0), TokenValue::DecValue(-1.0)],
+                    vec![TokenValue::DecValue(0.0), TokenValue::DecValue(0.5)],
+                ],
+                Span::default()
+            ),
+        );
+
+        // Small entries are not singular as long as the matrix isn't
+        let out = p.parse(lex_line("((1e-13 0) (0 1e-13)) inv")).unwrap();
+        assert_eq!(out.to_string(), "((10000000000000 0) (0 10000000000000))");
+
+        // 2x + y = 5 and x + 3y = 10
+        assert_eq!(
+            p.parse(lex_line("((2 1) (1 3)) (5 10) solve")).unwrap(),
+            vector(vec![TokenValue::IntValue(1), TokenValue::IntValue(3)]),
+        );
+        assert_eq!(
+            p.parse(lex_line("((2 1) (1 3)) (5 11) solve")).unwrap(),
+            vector(vec![
+                TokenValue::RatioValue(Ratio::new(4, 5).unwrap()),
+                TokenValue::RatioValue(Ratio::new(17, 5).unwrap()),
+            ]),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("m 1 get")).unwrap(),
+            vector(vec![TokenValue::IntValue(3), TokenValue::IntValue(4)]),
+        );
+    }
+
+    #[test]
+    fn parse_matrix_error_test() {
+        let mut p = create_parser(false);
+
+        let err = p.parse(lex_line("((1 2) (3 4) (5 6)) det")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+        assert_eq!(err.message(), "value of size 3x2 is not square");
+
+        let err = p.parse(lex_line("((1 2) (3 4 5))")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+
+        let err = p.parse(lex_line("((1 2) (3 4)) ((1 2 3)) *")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+
+        let err = p.parse(lex_line("((1 2) (2 4)) inv")).unwrap_err();
+        assert_eq!(err.code(), "E10");
+
+        let err = p.parse(lex_line("((1 2) (2 4)) (1 1) solve")).unwrap_err();
+        assert_eq!(err.code(), "E10");
+
+        let err = p.parse(lex_line("((1 2) (3 4)) (1 1) +")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("(1 2) transpose")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("0 identity")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+
+        let err = p.parse(lex_line("1000000000 identity")).unwrap_err();
+        assert_eq!(err.code(), "E7");
+    }
+
+    fn ratio(numer: i64, denom: i64) -> Value {
+        Value::ratio(Ratio::new(numer, denom).unwrap(), Span::default())
+    }
+
+    #[test]
+    fn parse_ratio_test() {
+        let mut p = create_parser(false);
+
+        assert_eq!(p.parse(lex_line("1 3 / ratio")).unwrap(), ratio(1, 3));
+        assert_eq!(p.parse(lex_line("1 3 / ratio")).unwrap().to_string(), "1/3");
+
+        // Ratio math stays exact
+        assert_eq!(
+            p.parse(lex_line("1 3 / ratio 1 6 / ratio +")).unwrap(),
+            ratio(1, 2),
+        );
+        assert_eq!(p.parse(lex_line("1 3 / ratio 3 *")).unwrap(), ratio(1, 1));
+        assert_eq!(p.parse(lex_line("2 ratio 3 /")).unwrap(), ratio(2, 3));
+        assert_eq!(p.parse(lex_line("2 3 / ratio -2 ^")).unwrap(), ratio(9, 4));
+        assert_eq!(p.parse(lex_line("1 4 / ratio neg")).unwrap(), ratio(-1, 4));
+
+        // Anything with a decimal is a decimal
+        assert_eq!(
+            p.parse(lex_line("1 2 / ratio 0.25 +")).unwrap(),
+            Value::dec(0.75, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("1 4 / ratio dec")).unwrap(),
+            Value::dec(0.25, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("7 2 / ratio int")).unwrap(),
+            Value::int(3, Span::default()),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("(1 2) 2 ratio /")).unwrap(),
+            Value::vector(
+                vec![
+                    TokenValue::RatioValue(Ratio::new(1, 2).unwrap()),
+                    TokenValue::RatioValue(Ratio::new(1, 1).unwrap()),
+                ],
+                Span::default()
+            ),
+        );
+
+        let err = p.parse(lex_line("1 ratio 0 /")).unwrap_err();
+        assert_eq!(err.code(), "E11");
+
+        let err = p.parse(lex_line("a ratio")).unwrap_err();
+        assert_eq!(err.code(), "E3");
+    }
+
+    #[test]
+    fn parse_complex_test() {
+        let mut p = create_parser(false);
+
+        let complex = |re, im| Value::complex(Complex::new(re, im), Span::default());
+
+        assert_eq!(p.parse(lex_line("3i")).unwrap(), complex(0.0, 3.0));
+        assert_eq!(p.parse(lex_line("2 3i +")).unwrap(), complex(2.0, 3.0));
+        assert_eq!(
+            p.parse(lex_line("2 3 imaginary +")).unwrap(),
+            complex(2.0, 3.0)
+        );
+        assert_eq!(p.parse(lex_line("1i 2 ^")).unwrap(), complex(-1.0, 0.0));
+        assert_eq!(p.parse(lex_line("-4 sqrt")).unwrap(), complex(0.0, 2.0));
+        assert_eq!(
+            p.parse(lex_line("1 2i + 3 -1i + *")).unwrap(),
+            complex(5.0, 5.0),
+        );
+        assert_eq!(p.parse(lex_line("5 complex")).unwrap(), complex(5.0, 0.0));
+        assert_eq!(p.parse(lex_line("3 4i - conj")).unwrap(), complex(3.0, 4.0));
+        assert_eq!(
+            p.parse(lex_line("2 3i + neg")).unwrap(),
+            complex(-2.0, -3.0)
+        );
+        assert_eq!(p.parse(lex_line("2 3i +")).unwrap().to_string(), "2+3i");
+
+        assert_eq!(
+            p.parse(lex_line("3 4i + abs")).unwrap(),
+            Value::dec(5.0, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("1 2i + re")).unwrap(),
+            Value::dec(1.0, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("1 2i + im")).unwrap(),
+            Value::dec(2.0, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("-1 complex arg")).unwrap(),
+            Value::dec(std::f64::consts::PI, Span::default()),
+        );
+
+        // Real numbers keep their type
+        assert_eq!(
+            p.parse(lex_line("-5 abs")).unwrap(),
+            Value::int(5, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("4 sqrt")).unwrap(),
+            Value::dec(2.0, Span::default()),
+        );
+
+        let err = p.parse(lex_line("1i int")).unwrap_err();
+        assert_eq!(err.code(), "E3");
+
+        // An infinite decimal has no int to be cast to
+        let err = p.parse(lex_line("1e308 10 * int")).unwrap_err();
+        assert_eq!(err.code(), "E3");
+
+        let err = p.parse(lex_line("(1 2) 1i *")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("(1 2) abs")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+    }
+
+    #[test]
+    fn parse_unit_test() {
+        let mut p = create_parser(false);
+
+        let unit = |text: &str| Unit::parse(text).unwrap();
+
+        assert_eq!(
+            p.parse(lex_line("300 {m/s}")).unwrap(),
+            Value::int(300, Span::default()).with_unit(unit("m/s")),
+        );
+        assert_eq!(
+            p.parse(lex_line("300 {m/s}")).unwrap().to_string(),
+            "300 m/s"
+        );
+
+        // Units of the same dimension are converted to the first one
+        assert_eq!(
+            p.parse(lex_line("5 {km} 300 {m} +")).unwrap(),
+            Value::dec(5.3, Span::default()).with_unit(unit("km")),
+        );
+        assert_eq!(
+            p.parse(lex_line("3 {m} 2 {s} /")).unwrap(),
+            Value::dec(1.5, Span::default()).with_unit(unit("m/s")),
+        );
+        assert_eq!(
+            p.parse(lex_line("3 {m} 2 ^")).unwrap(),
+            Value::int(9, Span::default()).with_unit(unit("m^2")),
+        );
+        assert_eq!(
+            p.parse(lex_line("4 {m^2} sqrt")).unwrap(),
+            Value::dec(2.0, Span::default()).with_unit(unit("m")),
+        );
+        assert_eq!(
+            p.parse(lex_line("10 {m} 2 {m} /")).unwrap(),
+            Value::int(5, Span::default()),
+        );
+
+        // `to` converts without changing the dimension
+        assert_eq!(
+            p.parse(lex_line("5 {km} to {m}")).unwrap(),
+            Value::dec(5000.0, Span::default()).with_unit(unit("m")),
+        );
+        assert_eq!(
+            p.parse(lex_line("2 {N} 3 {m} * to {J}")).unwrap(),
+            Value::int(6, Span::default()).with_unit(unit("J")),
+        );
+        assert_eq!(
+            p.parse(lex_line("(1 {m} 2 {m})")).unwrap(),
+            vector(vec![TokenValue::IntValue(1), TokenValue::IntValue(2)]).with_unit(unit("m")),
+        );
+
+        let err = p.parse(lex_line("5 {m} 3 {s} +")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p.parse(lex_line("5 {m} 1 +")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p.parse(lex_line("5 {m} to {s}")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p.parse(lex_line("4 {m} sqrt")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p.parse(lex_line("5 {parsec}")).unwrap_err();
+        assert_eq!(err.code(), "E13");
+
+        let err = p.parse(lex_line("5 {m} to 3")).unwrap_err();
+        assert_eq!(err.code(), "E13");
+
+        // Unit powers that get too big for an i32 are an error instead of overflowing
+        let err = p.parse(lex_line("1 {m} 2000000000 ^ 2 ^")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p
+            .parse(lex_line("1 {m^2000000000} 1 {m^2000000000} *"))
+            .unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p.parse(lex_line("1 1 {m^-2147483648} /")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p
+            .parse(lex_line("((1 0) (0 1)) {m^2000000000} det"))
+            .unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        assert_eq!(
+            p.parse(lex_line("1 {m^-2147483648} 1 /"))
+                .unwrap()
+                .to_string(),
+            "1 1/m^2147483648"
+        );
+    }
+
+    #[test]
+    fn parse_string_test() {
+        let mut p = create_parser(false);
+
+        let string = |text: &str| Value::string(text, Span::default());
+
+        assert_eq!(p.parse(lex_line(r#""a b""#)).unwrap(), string("a b"));
+        assert_eq!(p.parse(lex_line(r#""a" 'b' +"#)).unwrap(), string("ab"));
+        assert_eq!(
+            p.parse(lex_line(r#""time: " 50 {s} +"#)).unwrap(),
+            string("time: 50 s"),
+        );
+        assert_eq!(p.parse(lex_line(r#"1.5 " m" +"#)).unwrap(), string("1.5 m"));
+        assert_eq!(
+            p.parse(lex_line(r#""a\tb" len"#)).unwrap(),
+            Value::int(3, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line(r#""héllo" len"#)).unwrap(),
+            Value::int(5, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("(1 2 3) len")).unwrap(),
+            Value::int(3, Span::default()),
+        );
+
+        // Strings can be kept in variables
+        p.parse(lex_line(r#""Component" name ="#)).unwrap();
+        assert_eq!(
+            p.parse(lex_line("name len")).unwrap(),
+            Value::int(9, Span::default())
+        );
+
+        // `print` takes the value off the stack
+        assert_eq!(
+            p.parse(lex_line(r#"1 "shown" print"#)).unwrap(),
+            Value::int(1, Span::default())
+        );
+
+        let err = p.parse(lex_line(r#""a" 1 -"#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        match p.parse(lex_line(r#""a" b +"#)) {
+            Err(ComponentError::WrongType { values, .. }) => assert_eq!(values[0].span.start, 4),
+            other => panic!("expected WrongType, got {:?}", other),
+        }
+
+        let err = p.parse(lex_line("5 len")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+    }
+
+    #[test]
+    fn parse_round_test() {
+        let mut p = create_parser(false);
+
+        let int = |v: i64| Value::int(v, Span::default());
+
+        // `round` used to truncate like `int`
+        assert_eq!(p.parse(lex_line("2.7 round")).unwrap(), int(3));
+        assert_eq!(p.parse(lex_line("2.7 int")).unwrap(), int(2));
+        assert_eq!(p.parse(lex_line("-2.5 round")).unwrap(), int(-3));
+        assert_eq!(p.parse(lex_line("2.5 floor")).unwrap(), int(2));
+        assert_eq!(p.parse(lex_line("-2.5 floor")).unwrap(), int(-3));
+        assert_eq!(p.parse(lex_line("2.1 ceil")).unwrap(), int(3));
+        assert_eq!(p.parse(lex_line("-2.7 trunc")).unwrap(), int(-2));
+        assert_eq!(p.parse(lex_line("7 2 / ratio round")).unwrap(), int(4));
+        assert_eq!(p.parse(lex_line("5 round")).unwrap(), int(5));
+        assert_eq!(
+            p.parse(lex_line("(1.4 2.6) round")).unwrap(),
+            vector(vec![TokenValue::IntValue(1), TokenValue::IntValue(3)]),
+        );
+
+        assert_eq!(
+            p.parse(lex_line("2.71828 2 round-to")).unwrap(),
+            Value::dec(2.72, Span::default()),
+        );
+        assert_eq!(p.parse(lex_line("1250 -2 round-to")).unwrap(), int(1300));
+        assert_eq!(
+            p.parse(lex_line("1 3 / ratio 2 round-to")).unwrap(),
+            ratio(33, 100)
+        );
+
+        let out = p.parse(lex_line("2.6 {m} round")).unwrap();
+        assert_eq!(out.to_string(), "3 m");
+
+        p.set_round_mode(RoundMode::HalfEven);
+        assert_eq!(p.parse(lex_line("2.5 round")).unwrap(), int(2));
+        assert_eq!(p.parse(lex_line("3.5 round")).unwrap(), int(4));
+        assert_eq!(p.parse(lex_line("1250 -2 round-to")).unwrap(), int(1200));
+
+        let err = p.parse(lex_line("1i round")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("2.5 0.5 round-to")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("round")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+    }
+
+    #[test]
+    fn parse_builtin_test() {
+        let mut p = create_parser(false);
+
+        let int = |v: i64| Value::int(v, Span::default());
+        let dec = |v: f64| Value::dec(v, Span::default());
+
+        assert_eq!(p.parse(lex_line("0 sin")).unwrap(), dec(0.0));
+        assert_eq!(p.parse(lex_line("0 cos")).unwrap(), dec(1.0));
+        assert_eq!(
+            p.parse(lex_line("1 0 atan2")).unwrap(),
+            dec(std::f64::consts::FRAC_PI_2)
+        );
+        assert_eq!(p.parse(lex_line("1000 log")).unwrap(), dec(3.0));
+        assert_eq!(p.parse(lex_line("8 log2")).unwrap(), dec(3.0));
+        assert_eq!(p.parse(lex_line("e ln")).unwrap(), dec(1.0));
+        assert_eq!(
+            p.parse(lex_line("-1 ln")).unwrap(),
+            Value::complex(Complex::new(0.0, std::f64::consts::PI), Span::default()),
+        );
+
+        // Other functions are complex outside of their real domain too, instead of `NaN`
+        for line in ["2 asin", "-2 acos", "-100 log", "-8 log2", "1i log"] {
+            let out = p.parse(lex_line(line)).unwrap();
+            assert_eq!(out.value_type, TokenType::ComplexLiteral);
+        }
+        assert_eq!(
+            p.parse(lex_line("-100 log")).unwrap().to_string(),
+            "2+1.3643763538418412i"
+        );
+        assert_eq!(p.parse(lex_line("1 acos")).unwrap(), dec(0.0));
+
+        assert_eq!(p.parse(lex_line("-7 3 mod")).unwrap(), int(2));
+        assert_eq!(p.parse(lex_line("7 -3 mod")).unwrap(), int(-2));
+        assert_eq!(p.parse(lex_line("7.5 2 mod")).unwrap(), dec(1.5));
+        assert_eq!(p.parse(lex_line("7 2 / ratio 1 mod")).unwrap(), ratio(1, 2));
+        assert_eq!(p.parse(lex_line("12 18 gcd")).unwrap(), int(6));
+        assert_eq!(p.parse(lex_line("4 -6 lcm")).unwrap(), int(12));
+        assert_eq!(p.parse(lex_line("5 fact")).unwrap(), int(120));
+        assert_eq!(p.parse(lex_line("0 fact")).unwrap(), int(1));
+        assert_eq!(p.parse(lex_line("2 5 min")).unwrap(), int(2));
+        assert_eq!(p.parse(lex_line("2 5.5 max")).unwrap(), dec(5.5));
+
+        let out = p.parse(lex_line("30 fact")).unwrap();
+        assert_eq!(out.to_string(), "265252859812191058636308480000000");
+
+        let out = p.parse(lex_line("3 {m} 2 {km} max")).unwrap();
+        assert_eq!(out.to_string(), "2000 m");
+
+        // Every function checks how many values it needs
+        let err = p.parse(lex_line("sin")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+
+        let err = p.parse(lex_line("1 gcd")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+
+        let err = p.parse(lex_line("(1 2) sin")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("1.5 2 gcd")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("-1 fact")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("100000 fact")).unwrap_err();
+        assert_eq!(err.code(), "E15");
+
+        let err = p.parse(lex_line("5 0 mod")).unwrap_err();
+        assert_eq!(err.code(), "E11");
+
+        let err = p.parse(lex_line("1 {m} sin")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        // Built-in names can't be used for functions
+        let err = p.parse(lex_line("> 1 + sin fn")).unwrap_err();
+        assert_eq!(err.code(), "E6");
+    }
+
+    #[test]
+    fn parse_int_division_test() {
+        let mut p = create_parser(false);
+
+        let int = |v: i64| Value::int(v, Span::default());
+
+        assert_eq!(p.parse(lex_line("7 2 //")).unwrap(), int(3));
+        assert_eq!(p.parse(lex_line("-7 2 //")).unwrap(), int(-4));
+        assert_eq!(p.parse(lex_line("7.5 2 //")).unwrap(), int(3));
+        assert_eq!(p.parse(lex_line("-7 2 %")).unwrap(), int(1));
+        assert_eq!(p.parse(lex_line("7 -2 %")).unwrap(), int(-1));
+        assert_eq!(
+            p.parse(lex_line("7.5 2 %")).unwrap(),
+            Value::dec(1.5, Span::default()),
+        );
+        assert_eq!(
+            p.parse(lex_line("7 2 / ratio 1 3 / ratio %")).unwrap(),
+            ratio(1, 6)
+        );
+        assert_eq!(
+            p.parse(lex_line("(7 8 9) 4 %")).unwrap(),
+            vector(vec![
+                TokenValue::IntValue(3),
+                TokenValue::IntValue(0),
+                TokenValue::IntValue(1),
+            ]),
+        );
+
+        let big = p.parse(lex_line("2 100 ^ 3 //")).unwrap();
+        assert_eq!(big.to_string(), "422550200076076467165567735125");
+        assert_eq!(p.parse(lex_line("2 100 ^ 3 %")).unwrap(), int(1));
+
+        let out = p.parse(lex_line("7 {m} 2 {m} //")).unwrap();
+        assert_eq!(out, int(3));
+
+        // Every kind of division by zero is an error instead of `inf` or `NaN`
+        for line in [
+            "1 0 /",
+            "1.5 0 /",
+            "1 0.0 //",
+            "5 0 %",
+            "(1 2) (1 0) /",
+            "1 0i /",
+            "0 -1 ^",
+            "0.0 -0.5 ^",
+            "(1 0) -2 ^",
+        ] {
+            let err = p.parse(lex_line(line)).unwrap_err();
+            assert_eq!(err.code(), "E11", "{}", line);
+        }
+
+        let err = p.parse(lex_line("1i 2 %")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        // Zero to a power that isn't negative is still fine
+        assert_eq!(p.parse(lex_line("0 2 ^")).unwrap(), int(0));
+        assert_eq!(p.parse(lex_line("0 0 ^")).unwrap(), int(1));
+        assert_eq!(
+            p.parse(lex_line("(0 2) (1 -1) ^")).unwrap(),
+            vector(vec![TokenValue::IntValue(0), TokenValue::DecValue(0.5)]),
+        );
+    }
+
+    #[test]
+    fn parse_stack_word_test() {
+        let mut p = create_parser(false);
+
+        let int = |v: i64| Value::int(v, Span::default());
+
+        assert_eq!(p.parse(lex_line("3 dup *")).unwrap(), int(9));
+        p.parse(lex_line("clear")).unwrap();
+        assert_eq!(p.parse(lex_line("1 2 drop")).unwrap(), int(1));
+        p.parse(lex_line("clear")).unwrap();
+        assert_eq!(p.parse(lex_line("1 2 swap -")).unwrap(), int(1));
+        p.parse(lex_line("clear")).unwrap();
+        // The value a line gives back is taken off the stack
+        assert_eq!(p.parse(lex_line("1 2 over")).unwrap(), int(1));
+        assert_eq!(p.parse(lex_line("depth")).unwrap(), int(2));
+        p.parse(lex_line("clear")).unwrap();
+        assert_eq!(p.parse(lex_line("1 2 3 rot")).unwrap(), int(1));
+        assert_eq!(p.parse(lex_line("drop")).unwrap(), int(2));
+        p.parse(lex_line("clear")).unwrap();
+
+        assert_eq!(p.parse(lex_line("1 2 3 2 pick")).unwrap(), int(1));
+        assert_eq!(p.parse(lex_line("depth")).unwrap(), int(3));
+        p.parse(lex_line("clear")).unwrap();
+        assert_eq!(p.parse(lex_line("1 2 3 2 roll")).unwrap(), int(1));
+        assert_eq!(p.parse(lex_line("depth")).unwrap(), int(2));
+        p.parse(lex_line("clear")).unwrap();
+        assert_eq!(p.parse(lex_line("depth")).unwrap(), int(0));
+
+        let out = p.parse(lex_line("clear")).unwrap();
+        assert_eq!(out.value_type, TokenType::NoType);
+
+        for line in ["dup", "1 swap", "1 2 rot", "1 1 pick", "0 roll"] {
+            let err = p.parse(lex_line(line)).unwrap_err();
+            assert_eq!(err.code(), "E4", "{}", line);
+        }
+
+        let err = p.parse(lex_line("1 -1 pick")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+    }
+
+    #[test]
+    fn parse_compare_test() {
+        let mut p = create_parser(false);
+
+        let yes = Value::boolean(true, Span::default());
+        let no = Value::boolean(false, Span::default());
+
+        assert_eq!(p.parse(lex_line("1 2 <")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("1 2 >")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("2 2 <=")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("1.5 2 >=")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("1 2 / ratio 0.5 ==")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("1 3 / ratio 1 3 / !=")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("2 64 ^ 2 64 ^ 1 + <")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("1 {km} 500 {m} >")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("(1 2) (1 2) ==")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("(1 2) (1 2 3) ==")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("1i 1i ==")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line(r#""abc" "abd" <"#)).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("true 1 ==")).unwrap(), yes);
+
+        assert_eq!(p.parse(lex_line("1 0 and")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("1 0 or")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("true true xor")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("1 2 < 3 2 < or")).unwrap(), yes);
+
+        // A `>` with an `fn` after it still starts a function
+        p.parse(lex_line("> 2 * double fn")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("3 double")).unwrap(),
+            Value::int(6, Span::default())
+        );
+        p.parse(lex_line("1 > 3 * triple fn")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("2 triple")).unwrap(),
+            Value::int(6, Span::default())
+        );
+
+        let err = p.parse(lex_line(r#""a" 1 =="#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("1i 2 <")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("1 {m} 1 {s} <")).unwrap_err();
+        assert_eq!(err.code(), "E12");
+
+        let err = p.parse(lex_line(r#""a" 1 and"#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("1 <")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+    }
+
+    #[test]
+    fn parse_bool_test() {
+        let mut p = create_parser(false);
+
+        let yes = Value::boolean(true, Span::default());
+        let no = Value::boolean(false, Span::default());
+
+        // `!` takes one value and gives a boolean, not a string
+        assert_eq!(p.parse(lex_line("true !")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("false !")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("0 !")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("2 !")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("0.5 !")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("true ! !")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("1 2 < !")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("true !")).unwrap().to_string(), "0");
+
+        // It only uses the top value, so the one under it is left alone
+        assert_eq!(p.parse(lex_line("5 true !")).unwrap(), no);
+        assert_eq!(
+            p.parse(lex_line("depth")).unwrap(),
+            Value::int(1, Span::default())
+        );
+        p.parse(lex_line("clear")).unwrap();
+
+        // `bool` and `!` agree on what is true
+        assert_eq!(p.parse(lex_line("2 bool")).unwrap(), yes);
+        assert_eq!(p.parse(lex_line("0 bool")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("-0.0 bool")).unwrap(), no);
+        assert_eq!(p.parse(lex_line("true bool")).unwrap(), yes);
+        assert_eq!(
+            p.parse(lex_line("true int")).unwrap(),
+            Value::int(1, Span::default())
+        );
+
+        // `?` assigns when the condition is true in the same way
+        p.parse(lex_line("true a =")).unwrap();
+        p.parse(lex_line("5 b a ?")).unwrap();
+        assert_eq!(
+            p.local_memory.get("b").unwrap().value,
+            TokenValue::IntValue(5)
+        );
+
+        p.parse(lex_line("6 b 2 ?")).unwrap();
+        assert_eq!(
+            p.local_memory.get("b").unwrap().value,
+            TokenValue::IntValue(6)
+        );
+
+        p.parse(lex_line("7 b a ! ?")).unwrap();
+        p.parse(lex_line("8 b 0 ?")).unwrap();
+        p.parse(lex_line("9 b 3 2 < ?")).unwrap();
+        assert_eq!(
+            p.local_memory.get("b").unwrap().value,
+            TokenValue::IntValue(6)
+        );
+
+        let err = p.parse(lex_line(r#""a" !"#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line(r#"1 c "yes" ?"#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line(r#""yes" bool"#)).unwrap_err();
+        assert_eq!(err.code(), "E3");
+
+        let err = p.parse(lex_line("!")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+
+        let err = p.parse(lex_line("c true ?")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+    }
+
+    #[test]
+    fn parse_quotation_test() {
+        let mut p = create_parser(false);
+
+        // The code in a quotation isn't run until it is called
+        let quote = p.parse(lex_line("[2 *]")).unwrap();
+        assert_eq!(quote.value_type, TokenType::QuotationLiteral);
+        assert_eq!(quote.to_string(), "[2 *]");
+        assert_eq!(
+            p.parse(lex_line("5 [2 *] call")).unwrap(),
+            Value::int(10, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("[1 [2 3 +] call +] call")).unwrap(),
+            Value::int(6, Span::default())
+        );
+
+        // Quotations can be kept in variables
+        p.parse(lex_line("[1 +] inc =")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("4 inc call")).unwrap(),
+            Value::int(5, Span::default())
+        );
+
+        assert_eq!(
+            p.parse(lex_line("3 true [1 +] if")).unwrap(),
+            Value::int(4, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("3 0 [1 +] if")).unwrap(),
+            Value::int(3, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("1 2 < [10] [20] ifelse")).unwrap(),
+            Value::int(10, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("1 2 > [10] [20] ifelse")).unwrap(),
+            Value::int(20, Span::default())
+        );
+
+        // The quotation runs before the rest of the line
+        assert_eq!(
+            p.parse(lex_line("2 true [3 +] if 10 *")).unwrap(),
+            Value::int(50, Span::default())
+        );
+
+        let err = p.parse(lex_line("[1 2")).unwrap_err();
+        assert_eq!(err.code(), "E9");
+
+        let err = p.parse(lex_line("1 2 ]")).unwrap_err();
+        assert_eq!(err.code(), "E9");
+
+        let err = p.parse(lex_line("true 5 if")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line(r#""yes" [1] if"#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("[1] [2] ifelse")).unwrap_err();
+        assert_eq!(err.code(), "E4");
+    }
+
+    #[test]
+    fn parse_function_branch_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("> dup 0 < [neg] if abs2 fn")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("-7 abs2")).unwrap(),
+            Value::big_int(BigInt::from(7), Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("7 abs2")).unwrap(),
+            Value::int(7, Span::default())
+        );
+
+        // A function can call itself as long as a branch stops it
+        p.parse(lex_line("> dup 1 > [dup 1 - fac *] [drop 1] ifelse fac fn"))
+            .unwrap();
+        assert_eq!(
+            p.parse(lex_line("5 fac")).unwrap(),
+            Value::int(120, Span::default())
+        );
+
+        // A function runs where it is called and not at the end of the line
+        p.parse(lex_line("> 1 + inc fn")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("5 inc 2 *")).unwrap(),
+            Value::int(12, Span::default())
+        );
+    }
+
+    #[test]
+    fn parse_function_order_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("> 1 + inc fn")).unwrap();
+        p.parse(lex_line("> 10 * tens fn")).unwrap();
+        p.parse(lex_line("> inc tens bump fn")).unwrap();
+
+        // A function body runs where the function is called, before the rest of the line
+        assert_eq!(
+            p.parse(lex_line("5 inc 2 *")).unwrap(),
+            Value::int(12, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("2 tens inc")).unwrap(),
+            Value::int(21, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("2 inc tens")).unwrap(),
+            Value::int(30, Span::default())
+        );
+
+        // Functions called from functions run in order too
+        assert_eq!(
+            p.parse(lex_line("1 bump 3 -")).unwrap(),
+            Value::int(17, Span::default())
+        );
+    }
+
+    #[test]
+    fn parse_function_error_span_test() {
+        let mut p = create_parser(false);
+
+        p.parse(lex_line("> 2 0 / f fn")).unwrap();
+
+        // An error in a function body points at where the function was called
+        match p.parse(lex_line("abcdefghij f")) {
+            Err(ComponentError::DivisionByZero { value }) => {
+                assert_eq!(value.span.start, 11);
+                assert_eq!(value.span.end, 12);
+            }
+            other => panic!("expected DivisionByZero, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_loop_test() {
+        let mut p = create_parser(false);
+
+        assert_eq!(
+            p.parse(lex_line("0 5 [2 +] times")).unwrap(),
+            Value::int(10, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("7 0 [2 +] times")).unwrap(),
+            Value::int(7, Span::default())
+        );
+
+        // `for` pushes each int before running the quotation
+        assert_eq!(
+            p.parse(lex_line("0 1 10 [+] for")).unwrap(),
+            Value::int(55, Span::default())
+        );
+        assert_eq!(
+            p.parse(lex_line("1 4 [] for depth")).unwrap(),
+            Value::int(4, Span::default())
+        );
+        p.parse(lex_line("clear")).unwrap();
+        assert_eq!(
+            p.parse(lex_line("0 3 1 [+] for")).unwrap(),
+            Value::int(0, Span::default())
+        );
+
+        assert_eq!(
+            p.parse(lex_line("1 [dup 100 <] [2 *] while")).unwrap(),
+            Value::int(128, Span::default())
+        );
+
+        // Newton's method for the square root of 2
+        p.parse(lex_line("1.0 g =")).unwrap();
+        p.parse(lex_line(
+            "[g g * 2 - abs 1e-12 >] [g g g * 2 - 2 g * / - g = drop] while",
+        ))
+        .unwrap();
+        let root = p.local_memory.get("g").unwrap().number().unwrap();
+        assert!((root - std::f64::consts::SQRT_2).abs() < 1e-12);
+
+        // Loops that run many times don't keep every token they have run
+        assert_eq!(
+            p.parse(lex_line("0 100000 [1 +] times")).unwrap(),
+            Value::int(100000, Span::default())
+        );
+        assert!(p.token_stack.len() < 100);
+
+        // The rest of the line runs after the loop is done
+        assert_eq!(
+            p.parse(lex_line("1 3 [2 *] times 1 +")).unwrap(),
+            Value::int(9, Span::default())
+        );
+
+        let err = p.parse(lex_line("2.5 [1] times")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("1 2 3 for")).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line(r#"[1 "a" +] [] while"#)).unwrap_err();
+        assert_eq!(err.code(), "E2");
+
+        let err = p.parse(lex_line("[1] times")).unwrap_err();
+        assert_eq!(err.code(), "E4");
     }
 }
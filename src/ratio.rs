@@ -0,0 +1,207 @@
+use std::fmt;
+
+/// Give up looking for a closer fraction after this many steps
+const MAX_FRACTION_STEPS: usize = 64;
+
+/// How close a fraction has to be to a decimal to be used for it
+const FRACTION_TOLERANCE: f64 = 1e-12;
+
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a as i64
+}
+
+/// An exact fraction that is always in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ratio {
+    numer: i64,
+    denom: i64,
+}
+
+impl Ratio {
+    /// Make a fraction in lowest terms, or `None` if the denominator is zero or it doesn't fit
+    pub fn new(numer: i64, denom: i64) -> Option<Ratio> {
+        if denom == 0 {
+            return None;
+        }
+
+        let divisor = gcd(numer, denom);
+        let (mut numer, mut denom) = (numer / divisor, denom / divisor);
+
+        if denom < 0 {
+            numer = numer.checked_neg()?;
+            denom = denom.checked_neg()?;
+        }
+
+        Some(Ratio { numer, denom })
+    }
+
+    pub fn from_int(value: i64) -> Ratio {
+        Ratio {
+            numer: value,
+            denom: 1,
+        }
+    }
+
+    /// The closest simple fraction to a decimal, found with continued fractions
+    ///
+    /// This is what lets `1 3 / ratio` give back `1/3` instead of the fraction that is exactly
+    /// equal to the decimal `0.3333333333333333`.
+    pub fn from_f64(value: f64) -> Option<Ratio> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        // The last two convergents h/k
+        let (mut h0, mut h1) = (0_i64, 1_i64);
+        let (mut k0, mut k1) = (1_i64, 0_i64);
+        let mut rest = value;
+
+        for _ in 0..MAX_FRACTION_STEPS {
+            let whole = rest.floor();
+            if whole.abs() >= i64::MAX as f64 {
+                break;
+            }
+
+            let a = whole as i64;
+            let h = a.checked_mul(h1).and_then(|v| v.checked_add(h0));
+            let k = a.checked_mul(k1).and_then(|v| v.checked_add(k0));
+
+            match (h, k) {
+                (Some(h), Some(k)) => {
+                    (h0, h1) = (h1, h);
+                    (k0, k1) = (k1, k);
+                }
+                _ => break,
+            }
+
+            if (value - h1 as f64 / k1 as f64).abs() <= value.abs() * FRACTION_TOLERANCE {
+                break;
+            }
+
+            let fraction = rest - whole;
+            if fraction == 0.0 {
+                break;
+            }
+            rest = 1.0 / fraction;
+        }
+
+        Ratio::new(h1, k1)
+    }
+
+    pub fn numer(&self) -> i64 {
+        self.numer
+    }
+
+    pub fn denom(&self) -> i64 {
+        self.denom
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numer == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    pub fn checked_add(self, other: Ratio) -> Option<Ratio> {
+        let numer = self
+            .numer
+            .checked_mul(other.denom)?
+            .checked_add(other.numer.checked_mul(self.denom)?)?;
+        Ratio::new(numer, self.denom.checked_mul(other.denom)?)
+    }
+
+    pub fn checked_sub(self, other: Ratio) -> Option<Ratio> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(
+            self.numer.checked_mul(other.numer)?,
+            self.denom.checked_mul(other.denom)?,
+        )
+    }
+
+    pub fn checked_div(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(
+            self.numer.checked_mul(other.denom)?,
+            self.denom.checked_mul(other.numer)?,
+        )
+    }
+
+    pub fn checked_neg(self) -> Option<Ratio> {
+        Some(Ratio {
+            numer: self.numer.checked_neg()?,
+            denom: self.denom,
+        })
+    }
+
+    /// Raise to a whole power, where a negative power flips the fraction
+    pub fn checked_pow(self, exp: i64) -> Option<Ratio> {
+        let power = u32::try_from(exp.unsigned_abs()).ok()?;
+        let raised = Ratio::new(
+            self.numer.checked_pow(power)?,
+            self.denom.checked_pow(power)?,
+        )?;
+
+        if exp < 0 {
+            Ratio::new(raised.denom, raised.numer)
+        } else {
+            Some(raised)
+        }
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.denom == 1 {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio_new_test() {
+        assert_eq!(Ratio::new(2, 4), Ratio::new(1, 2));
+        assert_eq!(Ratio::new(3, -6), Ratio::new(-1, 2));
+        assert_eq!(Ratio::new(0, 5), Ratio::new(0, 1));
+        assert_eq!(Ratio::new(1, 0), None);
+    }
+
+    #[test]
+    fn ratio_from_f64_test() {
+        assert_eq!(Ratio::from_f64(1.0 / 3.0), Ratio::new(1, 3));
+        assert_eq!(Ratio::from_f64(0.1), Ratio::new(1, 10));
+        assert_eq!(Ratio::from_f64(-2.5), Ratio::new(-5, 2));
+        assert_eq!(Ratio::from_f64(7.0), Ratio::new(7, 1));
+        assert_eq!(Ratio::from_f64(0.0), Ratio::new(0, 1));
+        assert_eq!(Ratio::from_f64(f64::NAN), None);
+    }
+
+    #[test]
+    fn ratio_arithmetic_test() {
+        let third = Ratio::new(1, 3).unwrap();
+        let half = Ratio::new(1, 2).unwrap();
+
+        assert_eq!(third.checked_add(half), Ratio::new(5, 6));
+        assert_eq!(third.checked_sub(half), Ratio::new(-1, 6));
+        assert_eq!(third.checked_mul(half), Ratio::new(1, 6));
+        assert_eq!(third.checked_div(half), Ratio::new(2, 3));
+        assert_eq!(half.checked_pow(-3), Ratio::new(8, 1));
+        assert_eq!(third.to_string(), "1/3");
+        assert_eq!(Ratio::from_int(4).to_string(), "4");
+    }
+}
@@ -0,0 +1,258 @@
+use super::error::ComponentError;
+use super::lexer::{Token, TokenType, TokenValue};
+use super::ratio::Ratio;
+use super::value::{int_value, numeric_types, Value, MAX_INT_BITS};
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed, Zero};
+use std::cmp::Ordering;
+
+/// Rounding to more places than this changes nothing, since no int has this many digits
+const MAX_PLACES: i64 = (MAX_INT_BITS / 3) as i64;
+
+/// How `round` picks between two ints when a number is exactly halfway between them
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum RoundMode {
+    /// `2.5` rounds to 3 and `-2.5` rounds to -3
+    #[default]
+    HalfAwayFromZero,
+    /// `2.5` rounds to 2 and `3.5` rounds to 4, which doesn't lean one way over many numbers
+    HalfEven,
+}
+
+/// Which way a number is rounded to an int
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+    Trunc,
+    Nearest(RoundMode),
+}
+
+impl Rounding {
+    fn dec(self, v: f64) -> f64 {
+        match self {
+            Rounding::Floor => v.floor(),
+            Rounding::Ceil => v.ceil(),
+            Rounding::Trunc => v.trunc(),
+            Rounding::Nearest(RoundMode::HalfAwayFromZero) => v.round(),
+            Rounding::Nearest(RoundMode::HalfEven) => v.round_ties_even(),
+        }
+    }
+
+    /// Round the fraction `numer/denom` exactly, where `denom` is positive
+    fn fraction(self, numer: &BigInt, denom: &BigInt) -> BigInt {
+        let mut floor = numer / denom;
+        if (numer % denom).is_negative() {
+            floor -= 1;
+        }
+
+        let rem = numer - &floor * denom;
+        if rem.is_zero() {
+            return floor;
+        }
+
+        let up = match self {
+            Rounding::Floor => false,
+            Rounding::Ceil => true,
+            Rounding::Trunc => numer.is_negative(),
+            Rounding::Nearest(mode) => match (&rem + &rem).cmp(denom) {
+                Ordering::Less => false,
+                Ordering::Greater => true,
+                Ordering::Equal => match mode {
+                    RoundMode::HalfAwayFromZero => !numer.is_negative(),
+                    RoundMode::HalfEven => !(&floor % BigInt::from(2)).is_zero(),
+                },
+            },
+        };
+
+        if up {
+            floor + 1
+        } else {
+            floor
+        }
+    }
+}
+
+fn pow10(exp: i64) -> BigInt {
+    BigInt::from(10).pow(exp as u32)
+}
+
+/// Round a number, or every number in a vector or matrix, to an int
+///
+/// Decimals that are infinite or not a number have no int to round to, so they are kept as is.
+pub fn rounded(value: &TokenValue, rounding: Rounding) -> Option<TokenValue> {
+    match value {
+        TokenValue::IntValue(_) | TokenValue::BigIntValue(_) => Some(value.clone()),
+        TokenValue::DecValue(v) => Some(
+            BigInt::from_f64(rounding.dec(*v))
+                .map(int_value)
+                .unwrap_or(TokenValue::DecValue(*v)),
+        ),
+        TokenValue::RatioValue(v) => Some(int_value(
+            rounding.fraction(&BigInt::from(v.numer()), &BigInt::from(v.denom())),
+        )),
+        TokenValue::VecValue(v) => v
+            .iter()
+            .map(|x| rounded(x, rounding))
+            .collect::<Option<Vec<_>>>()
+            .map(TokenValue::VecValue),
+        _ => None,
+    }
+}
+
+/// Round a number to a number of decimal places, or to tens, hundreds and so on when it is negative
+///
+/// Ints and ratios are rounded exactly and stay ints and ratios where they can.
+fn rounded_to(value: &TokenValue, places: i64, mode: RoundMode) -> Option<TokenValue> {
+    let rounding = Rounding::Nearest(mode);
+
+    match value {
+        TokenValue::IntValue(_) | TokenValue::BigIntValue(_) if places >= 0 => Some(value.clone()),
+        TokenValue::IntValue(v) => {
+            rounded_to(&TokenValue::BigIntValue(BigInt::from(*v)), places, mode)
+        }
+        TokenValue::BigIntValue(v) => {
+            let scale = pow10(-places);
+            Some(int_value(rounding.fraction(v, &scale) * scale))
+        }
+        TokenValue::DecValue(v) => {
+            let scale = 10f64.powi(places.clamp(-400, 400) as i32);
+            let out = if !v.is_finite() || (v * scale).abs() >= 2f64.powi(53) {
+                // Past this many places a decimal has nothing left to round
+                *v
+            } else if scale == 0.0 {
+                0.0
+            } else {
+                rounding.dec(v * scale) / scale
+            };
+            Some(TokenValue::DecValue(out))
+        }
+        TokenValue::RatioValue(v) if places < 0 => {
+            let scale = pow10(-places);
+            let whole = rounding.fraction(&BigInt::from(v.numer()), &(v.denom() * &scale));
+            Some(int_value(whole * scale))
+        }
+        TokenValue::RatioValue(v) => {
+            let scale = pow10(places);
+            let numer = rounding.fraction(&(v.numer() * &scale), &BigInt::from(v.denom()));
+
+            // A fraction too big for a ratio is rounded as a decimal instead
+            match (i64::try_from(numer), i64::try_from(scale)) {
+                (Ok(n), Ok(d)) => Ratio::new(n, d).map(TokenValue::RatioValue),
+                _ => rounded_to(&TokenValue::DecValue(v.to_f64()), places, mode),
+            }
+        }
+        TokenValue::VecValue(v) => v
+            .iter()
+            .map(|x| rounded_to(x, places, mode))
+            .collect::<Option<Vec<_>>>()
+            .map(TokenValue::VecValue),
+        _ => None,
+    }
+}
+
+fn round_types() -> Vec<TokenType> {
+    let mut types = numeric_types();
+    types.push(TokenType::VectorLiteral);
+    types.push(TokenType::MatrixLiteral);
+    types
+}
+
+/// Round a number to an int with `round`, `floor`, `ceil` or `trunc`, keeping its unit
+pub fn round(a: Value, rounding: Rounding, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    match rounded(&a.value, rounding) {
+        Some(out) => Ok(Value::from_number(out, span).with_unit(a.unit)),
+        None => Err(ComponentError::WrongType {
+            values: vec![a],
+            expected: round_types(),
+        }),
+    }
+}
+
+/// Round a number to a number of decimal places like `3.14159 2 round-to`
+pub fn round_to(
+    a: Value,
+    places: Value,
+    mode: RoundMode,
+    operation: &Token,
+) -> Result<Value, ComponentError> {
+    let span = a.span.to(places.span).to(operation.span);
+
+    let places = match places.value {
+        TokenValue::IntValue(v) if places.unit.is_none() => v.clamp(-MAX_PLACES, MAX_PLACES),
+        _ => {
+            return Err(ComponentError::WrongType {
+                values: vec![places],
+                expected: vec![TokenType::NumericIntLiteral],
+            })
+        }
+    };
+
+    match rounded_to(&a.value, places, mode) {
+        Some(out) => Ok(Value::from_number(out, span).with_unit(a.unit)),
+        None => Err(ComponentError::WrongType {
+            values: vec![a],
+            expected: round_types(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nearest(numer: i64, denom: i64, mode: RoundMode) -> BigInt {
+        Rounding::Nearest(mode).fraction(&BigInt::from(numer), &BigInt::from(denom))
+    }
+
+    #[test]
+    fn round_fraction_test() {
+        let away = RoundMode::HalfAwayFromZero;
+        let even = RoundMode::HalfEven;
+
+        assert_eq!(nearest(5, 2, away), BigInt::from(3));
+        assert_eq!(nearest(-5, 2, away), BigInt::from(-3));
+        assert_eq!(nearest(5, 2, even), BigInt::from(2));
+        assert_eq!(nearest(7, 2, even), BigInt::from(4));
+        assert_eq!(nearest(-5, 2, even), BigInt::from(-2));
+        assert_eq!(nearest(7, 3, even), BigInt::from(2));
+        assert_eq!(nearest(-8, 3, away), BigInt::from(-3));
+
+        let (n, d) = (BigInt::from(-7), BigInt::from(2));
+        assert_eq!(Rounding::Floor.fraction(&n, &d), BigInt::from(-4));
+        assert_eq!(Rounding::Ceil.fraction(&n, &d), BigInt::from(-3));
+        assert_eq!(Rounding::Trunc.fraction(&n, &d), BigInt::from(-3));
+    }
+
+    #[test]
+    fn round_to_test() {
+        let away = RoundMode::HalfAwayFromZero;
+
+        assert_eq!(
+            rounded_to(&TokenValue::DecValue(1.23456), 2, away),
+            Some(TokenValue::DecValue(1.23))
+        );
+        assert_eq!(
+            rounded_to(&TokenValue::IntValue(1250), -2, away),
+            Some(TokenValue::IntValue(1300))
+        );
+        assert_eq!(
+            rounded_to(&TokenValue::IntValue(1250), -2, RoundMode::HalfEven),
+            Some(TokenValue::IntValue(1200))
+        );
+        assert_eq!(
+            rounded_to(&TokenValue::RatioValue(Ratio::new(1, 3).unwrap()), 2, away),
+            Some(TokenValue::RatioValue(Ratio::new(33, 100).unwrap()))
+        );
+        assert_eq!(
+            rounded_to(&TokenValue::DecValue(1.5), 1000, away),
+            Some(TokenValue::DecValue(1.5))
+        );
+        assert_eq!(
+            rounded_to(&TokenValue::DecValue(1.5), -1000, away),
+            Some(TokenValue::DecValue(0.0))
+        );
+    }
+}
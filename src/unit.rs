@@ -0,0 +1,342 @@
+use super::error::ComponentError;
+use super::lexer::Token;
+use super::value::{is_numeric, operand_types, scaled, Value};
+use std::fmt;
+
+/// Powers of the SI base units length, mass, time, current, temperature, amount and luminosity
+///
+/// These are wider than the powers of a unit, so working them out can't overflow.
+pub type Dimension = [i64; 7];
+
+const NONE: Dimension = [0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOSITY: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0];
+
+/// Every unit that can be written, with its dimension and how many SI units it is
+const UNITS: [(&str, Dimension, f64); 27] = [
+    ("m", LENGTH, 1.0),
+    ("km", LENGTH, 1000.0),
+    ("cm", LENGTH, 0.01),
+    ("mm", LENGTH, 0.001),
+    ("in", LENGTH, 0.0254),
+    ("ft", LENGTH, 0.3048),
+    ("mi", LENGTH, 1609.344),
+    ("g", MASS, 0.001),
+    ("kg", MASS, 1.0),
+    ("lb", MASS, 0.45359237),
+    ("s", TIME, 1.0),
+    ("ms", TIME, 0.001),
+    ("min", TIME, 60.0),
+    ("h", TIME, 3600.0),
+    ("day", TIME, 86400.0),
+    ("A", CURRENT, 1.0),
+    ("K", TEMPERATURE, 1.0),
+    ("mol", AMOUNT, 1.0),
+    ("cd", LUMINOSITY, 1.0),
+    ("L", VOLUME, 0.001),
+    ("Hz", FREQUENCY, 1.0),
+    ("N", FORCE, 1.0),
+    ("Pa", PRESSURE, 1.0),
+    ("J", ENERGY, 1.0),
+    ("W", POWER, 1.0),
+    ("C", CHARGE, 1.0),
+    ("V", VOLTAGE, 1.0),
+];
+
+fn lookup(name: &str) -> Option<(Dimension, f64)> {
+    UNITS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, dimension, scale)| (*dimension, *scale))
+}
+
+/// The unit of a value, kept as the units it was written with and their powers like `m*s^-1`
+///
+/// Keeping the written units instead of only the dimension means `5 {km}` still shows as km.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Unit {
+    factors: Vec<(String, i32)>,
+}
+
+impl Unit {
+    /// No unit, which is what plain numbers have
+    pub fn none() -> Unit {
+        Unit::default()
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// Read a unit like `m/s^2` or `kg*m/s^2`, giving back the first name that isn't a unit
+    pub fn parse(text: &str) -> Result<Unit, String> {
+        let mut unit = Unit::none();
+        let mut sign = 1;
+        let mut part = String::new();
+
+        for c in text.chars().chain(['*']) {
+            if c != '*' && c != '/' {
+                part.push(c);
+                continue;
+            }
+
+            let (name, power) = match part.split_once('^') {
+                Some((name, power)) => (name, power.parse::<i32>().map_err(|_| part.clone())?),
+                None => (part.as_str(), 1),
+            };
+
+            // `1/s` is a unit with nothing on top
+            if name != "1" {
+                if lookup(name).is_none() {
+                    return Err(name.to_string());
+                }
+                let power = power.checked_mul(sign).ok_or_else(|| part.clone())?;
+                unit = unit
+                    .mul(&Unit {
+                        factors: vec![(name.to_string(), power)],
+                    })
+                    .ok_or_else(|| part.clone())?;
+            }
+
+            part.clear();
+            sign = if c == '/' { -1 } else { 1 };
+        }
+
+        Ok(unit)
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        let mut dimension = NONE;
+
+        for (name, power) in &self.factors {
+            let (base, _) = lookup(name).expect("Units are checked when they are read");
+            for (d, b) in dimension.iter_mut().zip(base) {
+                *d += b * *power as i64;
+            }
+        }
+
+        dimension
+    }
+
+    /// How many SI units this unit is, like 1000 for km
+    pub fn scale(&self) -> f64 {
+        self.factors
+            .iter()
+            .map(|(name, power)| {
+                let (_, scale) = lookup(name).expect("Units are checked when they are read");
+                scale.powi(*power)
+            })
+            .product()
+    }
+
+    /// Multiply two units, where powers of the same unit add up and cancel out
+    ///
+    /// This and the other unit operations give `None` when a power gets too big for an i32.
+    pub fn mul(&self, other: &Unit) -> Option<Unit> {
+        let mut factors = self.factors.clone();
+
+        for (name, power) in &other.factors {
+            match factors.iter_mut().find(|(n, _)| n == name) {
+                Some((_, p)) => *p = p.checked_add(*power)?,
+                None => factors.push((name.clone(), *power)),
+            }
+        }
+
+        factors.retain(|(_, power)| *power != 0);
+        Some(Unit { factors })
+    }
+
+    pub fn div(&self, other: &Unit) -> Option<Unit> {
+        self.mul(&other.powi(-1)?)
+    }
+
+    pub fn powi(&self, exp: i32) -> Option<Unit> {
+        let factors = self
+            .factors
+            .iter()
+            .filter(|_| exp != 0)
+            .map(|(name, power)| Some((name.clone(), power.checked_mul(exp)?)))
+            .collect::<Option<_>>()?;
+
+        Some(Unit { factors })
+    }
+
+    /// The square root, which only exists when every power is even
+    pub fn sqrt(&self) -> Option<Unit> {
+        if self.factors.iter().any(|(_, power)| power % 2 != 0) {
+            return None;
+        }
+
+        Some(Unit {
+            factors: self
+                .factors
+                .iter()
+                .map(|(name, power)| (name.clone(), power / 2))
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let part = |name: &String, power: i64| match power {
+            1 => name.clone(),
+            _ => format!("{}^{}", name, power),
+        };
+
+        let top: Vec<String> = self
+            .factors
+            .iter()
+            .filter(|(_, power)| *power > 0)
+            .map(|(name, power)| part(name, *power as i64))
+            .collect();
+        let bottom: Vec<String> = self
+            .factors
+            .iter()
+            .filter(|(_, power)| *power < 0)
+            .map(|(name, power)| part(name, -(*power as i64)))
+            .collect();
+
+        match (top.is_empty(), bottom.is_empty()) {
+            (_, true) => write!(f, "{}", top.join("*")),
+            (true, false) => write!(f, "1/{}", bottom.join("/")),
+            (false, false) => write!(f, "{}/{}", top.join("*"), bottom.join("/")),
+        }
+    }
+}
+
+/// The error for a unit with powers too big to keep, like `1 {m} 2000000000 ^ 2 ^`
+pub fn too_big(values: Vec<Value>) -> ComponentError {
+    ComponentError::DimensionMismatch {
+        values: values.into_iter().filter(|v| !v.unit.is_none()).collect(),
+        expected: Some("a unit with powers small enough to keep".to_string()),
+    }
+}
+
+fn unit_of(token: &Token) -> Result<Unit, ComponentError> {
+    Unit::parse(&token.value).map_err(|_| ComponentError::UnknownUnit {
+        unit: token.clone(),
+    })
+}
+
+/// Give a value a unit like `300 {m/s}`, which multiplies with any unit it already has
+pub fn attach(a: Value, unit: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(unit.span);
+    let written = unit_of(unit)?;
+
+    if !is_numeric(&a) {
+        return Err(ComponentError::WrongType {
+            values: vec![a],
+            expected: operand_types(),
+        });
+    }
+
+    let unit = match a.unit.mul(&written) {
+        Some(unit) => unit,
+        None => return Err(too_big(vec![a])),
+    };
+    Ok(Value { span, unit, ..a })
+}
+
+/// Convert a value to another unit of the same dimension, like `5 {km} to {m}`
+pub fn convert(a: Value, unit: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(unit.span);
+    let target = unit_of(unit)?;
+
+    if !is_numeric(&a) {
+        return Err(ComponentError::WrongType {
+            values: vec![a],
+            expected: operand_types(),
+        });
+    }
+
+    if a.unit.dimension() != target.dimension() {
+        return Err(ComponentError::DimensionMismatch {
+            values: vec![a],
+            expected: Some(format!("convertible to {}", target)),
+        });
+    }
+
+    // Units with the same scale don't need to turn the value into a decimal
+    let factor = a.unit.scale() / target.scale();
+    let value = if factor == 1.0 {
+        a.value
+    } else {
+        scaled(&a.value, factor)
+    };
+
+    Ok(Value::from_number(value, span).with_unit(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_parse_test() {
+        let speed = Unit::parse("m/s").unwrap();
+        assert_eq!(speed.dimension(), [1, 0, -1, 0, 0, 0, 0]);
+        assert_eq!(speed.to_string(), "m/s");
+
+        let force = Unit::parse("kg*m/s^2").unwrap();
+        assert_eq!(force.dimension(), Unit::parse("N").unwrap().dimension());
+        assert_eq!(force.to_string(), "kg*m/s^2");
+
+        assert_eq!(Unit::parse("1/s").unwrap().to_string(), "1/s");
+        assert_eq!(Unit::parse("m/m").unwrap(), Unit::none());
+        assert_eq!(Unit::parse("m/parsec"), Err("parsec".to_string()));
+        assert_eq!(Unit::parse("m^x"), Err("m^x".to_string()));
+    }
+
+    #[test]
+    fn unit_arithmetic_test() {
+        let m = Unit::parse("m").unwrap();
+        let s = Unit::parse("s").unwrap();
+
+        assert_eq!(m.div(&s).and_then(|u| u.mul(&s)), Some(m.clone()));
+        assert_eq!(m.mul(&m).unwrap().to_string(), "m^2");
+        assert_eq!(m.mul(&m).unwrap().sqrt(), Some(m.clone()));
+        assert_eq!(m.sqrt(), None);
+        assert_eq!(m.powi(0), Some(Unit::none()));
+        assert_eq!(Unit::parse("km/h").unwrap().scale(), 1000.0 / 3600.0);
+    }
+
+    #[test]
+    fn unit_overflow_test() {
+        let big = Unit::parse("m^2000000000").unwrap();
+        assert_eq!(big.mul(&big), None);
+        assert_eq!(big.powi(2), None);
+
+        let smallest = Unit::parse("m^-2147483648").unwrap();
+        assert_eq!(smallest.powi(-1), None);
+        assert_eq!(Unit::none().div(&smallest), None);
+        assert_eq!(smallest.to_string(), "1/m^2147483648");
+
+        // Units that are too big to read are reported like powers that can't be read
+        assert_eq!(
+            Unit::parse("m^2000000000*m^2000000000"),
+            Err("m^2000000000".to_string())
+        );
+        assert_eq!(
+            Unit::parse("1/m^-2147483648"),
+            Err("m^-2147483648".to_string())
+        );
+
+        // Dimensions are wider than powers, so they can always be worked out
+        let force = Unit::parse("N^2000000000").unwrap();
+        assert_eq!(force.dimension()[2], -4000000000);
+    }
+}
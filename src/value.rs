@@ -0,0 +1,961 @@
+use super::complex::{complex_of, Complex};
+use super::error::ComponentError;
+use super::lexer::{Span, Token, TokenType, TokenValue};
+use super::matrix;
+use super::ratio::Ratio;
+use super::round::{rounded, Rounding};
+use super::unit::{too_big, Unit};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::fmt;
+
+/// Ints bigger than this many bits are too big to work out exactly, so they are an error
+pub const MAX_INT_BITS: u64 = 1 << 20;
+
+/// Decimals at least this big are written in scientific notation, since they are past where
+/// every int fits in a decimal and would look exact otherwise
+const LARGE_DEC: f64 = 1e16;
+
+/// Decimals smaller than this are written in scientific notation instead of with lots of zeros
+const SMALL_DEC: f64 = 1e-7;
+
+/// A decimal written the usual way, or like `6.022e23` when it is very big or very small
+pub fn dec_string(v: f64) -> String {
+    let size = v.abs();
+
+    if size.is_finite() && size != 0.0 && !(SMALL_DEC..LARGE_DEC).contains(&size) {
+        format!("{:e}", v)
+    } else {
+        v.to_string()
+    }
+}
+
+/// A value on the stack or in a variable while a program is running
+///
+/// Tokens keep the text they were lexed from, values keep what that text means, so an int is an
+/// actual `i64` and arithmetic never has to parse strings.
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub value_type: TokenType,
+    pub value: TokenValue,
+    pub span: Span,
+    pub unit: Unit,
+}
+
+// Like tokens, values compare by what they are and not where they were written
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.value_type == other.value_type && self.value == other.value && self.unit == other.unit
+    }
+}
+
+impl Value {
+    /// The same value with a unit, like `300 {m/s}`
+    pub fn with_unit(self, unit: Unit) -> Value {
+        Value { unit, ..self }
+    }
+
+    pub fn int(value: i64, span: Span) -> Value {
+        Value {
+            value_type: TokenType::NumericIntLiteral,
+            value: TokenValue::IntValue(value),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    /// An int of any size
+    pub fn big_int(value: BigInt, span: Span) -> Value {
+        Value {
+            value_type: TokenType::NumericIntLiteral,
+            value: int_value(value),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn dec(value: f64, span: Span) -> Value {
+        Value {
+            value_type: TokenType::NumericDecLiteral,
+            value: TokenValue::DecValue(value),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn ratio(value: Ratio, span: Span) -> Value {
+        Value {
+            value_type: TokenType::RatioLiteral,
+            value: TokenValue::RatioValue(value),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn complex(value: Complex, span: Span) -> Value {
+        Value {
+            value_type: TokenType::ComplexLiteral,
+            value: TokenValue::ComplexValue(value),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn boolean(value: bool, span: Span) -> Value {
+        Value {
+            value_type: TokenType::BoolLiteral,
+            value: TokenValue::BoolValue(value),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn vector(values: Vec<TokenValue>, span: Span) -> Value {
+        Value {
+            value_type: TokenType::VectorLiteral,
+            value: TokenValue::VecValue(values),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn matrix(rows: Vec<Vec<TokenValue>>, span: Span) -> Value {
+        Value {
+            value_type: TokenType::MatrixLiteral,
+            value: TokenValue::VecValue(rows.into_iter().map(TokenValue::VecValue).collect()),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    /// Wrap the result of a math operation, which is a number, a vector or a matrix
+    pub fn from_number(value: TokenValue, span: Span) -> Value {
+        match value {
+            TokenValue::IntValue(v) => Value::int(v, span),
+            TokenValue::BigIntValue(v) => Value::big_int(v, span),
+            TokenValue::RatioValue(v) => Value::ratio(v, span),
+            TokenValue::ComplexValue(v) => Value::complex(v, span),
+            // A matrix is a vector of rows
+            TokenValue::VecValue(v) if matches!(v.first(), Some(TokenValue::VecValue(_))) => {
+                Value {
+                    value_type: TokenType::MatrixLiteral,
+                    value: TokenValue::VecValue(v),
+                    span,
+                    unit: Unit::none(),
+                }
+            }
+            TokenValue::VecValue(v) => Value::vector(v, span),
+            other => Value {
+                value_type: TokenType::NumericDecLiteral,
+                value: other,
+                span,
+                unit: Unit::none(),
+            },
+        }
+    }
+
+    pub fn string(text: &str, span: Span) -> Value {
+        Value {
+            value_type: TokenType::StringLiteral,
+            value: TokenValue::StringValue(text.to_string()),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    /// Code in square brackets like `[2 *]`, kept as tokens until it is run
+    pub fn quotation(tokens: Vec<Token>, span: Span) -> Value {
+        Value {
+            value_type: TokenType::QuotationLiteral,
+            value: TokenValue::QuotationValue(tokens),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    pub fn identifier(name: &str, span: Span) -> Value {
+        Value {
+            value_type: TokenType::Identifier,
+            value: TokenValue::StringValue(name.to_string()),
+            span,
+            unit: Unit::none(),
+        }
+    }
+
+    /// The value of an empty stack
+    pub fn none() -> Value {
+        Value {
+            value_type: TokenType::NoType,
+            value: TokenValue::StringValue(String::new()),
+            span: Span::default(),
+            unit: Unit::none(),
+        }
+    }
+
+    /// Read the value of a literal or identifier token
+    pub fn from_token(token: &Token) -> Option<Value> {
+        match token.token_type {
+            TokenType::NumericIntLiteral => match token.value.parse::<i64>() {
+                Ok(v) => Some(Value::int(v, token.span)),
+                Err(_) => token
+                    .value
+                    .parse::<BigInt>()
+                    .ok()
+                    .map(|v| Value::big_int(v, token.span)),
+            },
+            // Literals like `1e400` are too big to be a decimal
+            TokenType::NumericDecLiteral => token
+                .value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| Value::dec(v, token.span)),
+            TokenType::ImaginaryLiteral => token
+                .value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| Value::complex(Complex::new(0.0, v), token.span)),
+            TokenType::StringLiteral => Some(Value::string(&token.value, token.span)),
+            TokenType::BoolLiteral => Some(Value::boolean(token.value == "true", token.span)),
+            TokenType::Identifier => Some(Value::identifier(&token.value, token.span)),
+            _ => None,
+        }
+    }
+
+    /// The name of an identifier
+    pub fn name(&self) -> Option<&str> {
+        match (&self.value_type, &self.value) {
+            (TokenType::Identifier, TokenValue::StringValue(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The value as a number if it is an int, a decimal or a ratio
+    pub fn number(&self) -> Option<f64> {
+        number_of(&self.value)
+    }
+
+    /// The components of a vector, or the rows of a matrix
+    pub fn components(&self) -> Option<&Vec<TokenValue>> {
+        match &self.value {
+            TokenValue::VecValue(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// How many components the value has, which is 1 for anything that isn't a vector
+    pub fn size(&self) -> usize {
+        self.components().map_or(1, |v| v.len())
+    }
+
+    /// The size of a vector like `3`, or the rows and columns of a matrix like `2x3`
+    pub fn shape(&self) -> String {
+        match self.value_type {
+            TokenType::MatrixLiteral => {
+                let columns = match self.components().and_then(|rows| rows.first()) {
+                    Some(TokenValue::VecValue(row)) => row.len(),
+                    _ => 0,
+                };
+                format!("{}x{}", self.size(), columns)
+            }
+            TokenType::VectorLiteral => self.size().to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// The value as a number if it can be cast to one, which includes booleans
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value {
+            TokenValue::BoolValue(b) => Some(b as i64 as f64),
+            _ => self.number(),
+        }
+    }
+}
+
+impl fmt::Display for TokenValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenValue::StringValue(s) => write!(f, "{}", s),
+            TokenValue::IntValue(v) => write!(f, "{}", v),
+            TokenValue::BigIntValue(v) => write!(f, "{}", v),
+            TokenValue::DecValue(v) => write!(f, "{}", dec_string(*v)),
+            TokenValue::RatioValue(v) => write!(f, "{}", v),
+            TokenValue::ComplexValue(v) => write!(f, "{}", v),
+            TokenValue::BoolValue(b) => write!(f, "{}", *b as i32),
+            TokenValue::VecValue(v) => {
+                let parts: Vec<String> = v.iter().map(|x| x.to_string()).collect();
+                write!(f, "({})", parts.join(" "))
+            }
+            TokenValue::QuotationValue(tokens) => {
+                // Written back the way it was typed, since the tokens only keep what is inside
+                // quotes and braces
+                let parts: Vec<String> = tokens
+                    .iter()
+                    .map(|t| match t.token_type {
+                        TokenType::StringLiteral => format!("{:?}", t.value),
+                        TokenType::UnitLiteral => format!("{{{}}}", t.value),
+                        _ => t.value.clone(),
+                    })
+                    .collect();
+                write!(f, "[{}]", parts.join(" "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unit.is_none() {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.unit)
+        }
+    }
+}
+
+/// The types the math functions work on
+pub fn numeric_types() -> Vec<TokenType> {
+    vec![
+        TokenType::NumericIntLiteral,
+        TokenType::NumericDecLiteral,
+        TokenType::RatioLiteral,
+    ]
+}
+
+/// The types the math operators work on
+pub fn operand_types() -> Vec<TokenType> {
+    vec![
+        TokenType::NumericIntLiteral,
+        TokenType::NumericDecLiteral,
+        TokenType::RatioLiteral,
+        TokenType::ComplexLiteral,
+        TokenType::VectorLiteral,
+        TokenType::MatrixLiteral,
+    ]
+}
+
+pub fn number_of(value: &TokenValue) -> Option<f64> {
+    match value {
+        TokenValue::IntValue(v) => Some(*v as f64),
+        TokenValue::BigIntValue(v) => v.to_f64(),
+        TokenValue::DecValue(v) => Some(*v),
+        TokenValue::RatioValue(v) => Some(v.to_f64()),
+        _ => None,
+    }
+}
+
+/// Ints that fit in an i64 are kept as one, so each int only has one way to be stored
+pub fn int_value(value: BigInt) -> TokenValue {
+    match value.to_i64() {
+        Some(v) => TokenValue::IntValue(v),
+        None => TokenValue::BigIntValue(value),
+    }
+}
+
+pub fn big_int_of(value: &TokenValue) -> Option<BigInt> {
+    match value {
+        TokenValue::IntValue(v) => Some(BigInt::from(*v)),
+        TokenValue::BigIntValue(v) => Some(v.clone()),
+        _ => None,
+    }
+}
+
+/// Ints can be used as ratios without losing anything
+pub fn ratio_of(value: &TokenValue) -> Option<Ratio> {
+    match value {
+        TokenValue::IntValue(v) => Some(Ratio::from_int(*v)),
+        TokenValue::RatioValue(v) => Some(*v),
+        _ => None,
+    }
+}
+
+fn ratio_arithmetic(op: &TokenType, a: Ratio, b: &TokenValue) -> Option<Ratio> {
+    match (op, b) {
+        // Only whole powers keep a ratio exact
+        (TokenType::Carrot, TokenValue::IntValue(e)) => a.checked_pow(*e),
+        (TokenType::Carrot, _) => None,
+        (TokenType::Addition, _) => a.checked_add(ratio_of(b)?),
+        (TokenType::Subtraction, _) => a.checked_sub(ratio_of(b)?),
+        (TokenType::Multiplication, _) => a.checked_mul(ratio_of(b)?),
+        (TokenType::Division, _) => a.checked_div(ratio_of(b)?),
+        _ => unreachable!(),
+    }
+}
+
+fn complex_arithmetic(op: &TokenType, a: Complex, b: &TokenValue) -> Option<Complex> {
+    match (op, b) {
+        // Whole powers are worked out by multiplying so that `1i 2 ^` is exactly -1
+        (TokenType::Carrot, TokenValue::IntValue(e)) => Some(a.powi(*e)),
+        (TokenType::Carrot, _) => Some(a.powc(complex_of(b)?)),
+        (TokenType::Addition, _) => Some(a + complex_of(b)?),
+        (TokenType::Subtraction, _) => Some(a - complex_of(b)?),
+        (TokenType::Multiplication, _) => Some(a * complex_of(b)?),
+        (TokenType::Division, _) => Some(a / complex_of(b)?),
+        _ => unreachable!(),
+    }
+}
+
+fn dec_arithmetic(op: &TokenType, a: f64, b: f64) -> f64 {
+    match op {
+        TokenType::Addition => a + b,
+        TokenType::Subtraction => a - b,
+        TokenType::Multiplication => a * b,
+        TokenType::Division => a / b,
+        TokenType::Carrot => a.powf(b),
+        _ => unreachable!(),
+    }
+}
+
+fn big_int_arithmetic(op: &TokenType, a: BigInt, b: BigInt) -> Option<BigInt> {
+    match op {
+        TokenType::Addition => Some(a + b),
+        TokenType::Subtraction => Some(a - b),
+        TokenType::Multiplication if a.bits() + b.bits() <= MAX_INT_BITS => Some(a * b),
+        TokenType::Multiplication => None,
+        TokenType::Division if !b.is_zero() && (&a % &b).is_zero() => Some(a / b),
+        TokenType::Division => None,
+        TokenType::Carrot => {
+            let exp = b.to_u32()?;
+            if a.bits().saturating_mul(exp as u64) > MAX_INT_BITS {
+                return None;
+            }
+            Some(a.pow(exp))
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn int_arithmetic(op: &TokenType, a: i64, b: i64) -> Option<i64> {
+    match op {
+        TokenType::Addition => a.checked_add(b),
+        TokenType::Subtraction => a.checked_sub(b),
+        TokenType::Multiplication => a.checked_mul(b),
+        // Division only stays an int when nothing is left over
+        TokenType::Division => match a.checked_rem(b) {
+            Some(0) => a.checked_div(b),
+            _ => None,
+        },
+        // A negative power is a fraction
+        TokenType::Carrot => u32::try_from(b).ok().and_then(|e| a.checked_pow(e)),
+        _ => unreachable!(),
+    }
+}
+
+/// The remainder of `a / b` with the same sign as `b`, so `-7 3 %` is 2
+///
+/// Ints and ratios stay exact, and anything else is a decimal. Gives back `None` for a remainder
+/// of dividing by zero.
+fn remainder(a: &TokenValue, b: &TokenValue) -> Option<TokenValue> {
+    let floored = |r: BigInt, b: &BigInt| {
+        if !r.is_zero() && r.is_negative() != b.is_negative() {
+            r + b
+        } else {
+            r
+        }
+    };
+
+    match (a, b) {
+        (
+            TokenValue::IntValue(_) | TokenValue::BigIntValue(_),
+            TokenValue::IntValue(_) | TokenValue::BigIntValue(_),
+        ) => {
+            let (x, y) = (big_int_of(a)?, big_int_of(b)?);
+            if y.is_zero() {
+                return None;
+            }
+            Some(int_value(floored(&x % &y, &y)))
+        }
+        (TokenValue::RatioValue(_), TokenValue::IntValue(_) | TokenValue::RatioValue(_))
+        | (TokenValue::IntValue(_), TokenValue::RatioValue(_)) => {
+            let (x, y) = (ratio_of(a)?, ratio_of(b)?);
+            if y.is_zero() {
+                return None;
+            }
+
+            // a - b * floor(a / b), which is a decimal if it doesn't fit in a ratio
+            let exact = x.checked_div(y).and_then(|q| {
+                let whole = Ratio::from_int(q.numer().div_euclid(q.denom()));
+                x.checked_sub(y.checked_mul(whole)?)
+            });
+            match exact {
+                Some(r) => Some(TokenValue::RatioValue(r)),
+                None => remainder(
+                    &TokenValue::DecValue(x.to_f64()),
+                    &TokenValue::DecValue(y.to_f64()),
+                ),
+            }
+        }
+        _ => {
+            let (x, y) = (number_of(a)?, number_of(b)?);
+            if y == 0.0 {
+                return None;
+            }
+            Some(TokenValue::DecValue(x - y * (x / y).floor()))
+        }
+    }
+}
+
+/// The whole number of times `b` goes into `a`, rounded down so `-7 2 //` is -4
+fn floor_division(a: &TokenValue, b: &TokenValue) -> Option<TokenValue> {
+    match (big_int_of(a), big_int_of(b)) {
+        (Some(x), Some(y)) => {
+            if y.is_zero() {
+                return None;
+            }
+
+            let q = &x / &y;
+            if !(&x % &y).is_zero() && x.is_negative() != y.is_negative() {
+                Some(int_value(q - 1))
+            } else {
+                Some(int_value(q))
+            }
+        }
+        _ => rounded(
+            &number_arithmetic(&TokenType::Division, a, b)?,
+            Rounding::Floor,
+        ),
+    }
+}
+
+/// Two ints give an int if the result is a whole number, and a ratio with an int or a ratio gives
+/// an exact ratio. Ints that get too big for an i64 are kept exactly as big ints. A complex number
+/// with anything gives a complex number, and otherwise the result is a decimal.
+pub fn number_arithmetic(op: &TokenType, a: &TokenValue, b: &TokenValue) -> Option<TokenValue> {
+    match op {
+        TokenType::Percent => return remainder(a, b),
+        TokenType::IntDivision => return floor_division(a, b),
+        _ => {}
+    }
+
+    match (a, b) {
+        (TokenValue::IntValue(x), TokenValue::IntValue(y)) => {
+            if let Some(v) = int_arithmetic(op, *x, *y) {
+                return Some(TokenValue::IntValue(v));
+            }
+            if let Some(v) = big_int_arithmetic(op, big_int_of(a)?, big_int_of(b)?) {
+                return Some(int_value(v));
+            }
+        }
+        (TokenValue::BigIntValue(_), TokenValue::IntValue(_) | TokenValue::BigIntValue(_))
+        | (TokenValue::IntValue(_), TokenValue::BigIntValue(_)) => {
+            if let Some(v) = big_int_arithmetic(op, big_int_of(a)?, big_int_of(b)?) {
+                return Some(int_value(v));
+            }
+        }
+        (TokenValue::RatioValue(_), TokenValue::IntValue(_) | TokenValue::RatioValue(_))
+        | (TokenValue::IntValue(_), TokenValue::RatioValue(_)) => {
+            if let Some(v) = ratio_arithmetic(op, ratio_of(a)?, b) {
+                return Some(TokenValue::RatioValue(v));
+            }
+        }
+        (TokenValue::ComplexValue(_), _) | (_, TokenValue::ComplexValue(_)) => {
+            return complex_arithmetic(op, complex_of(a)?, b).map(TokenValue::ComplexValue);
+        }
+        _ => {}
+    }
+
+    Some(TokenValue::DecValue(dec_arithmetic(
+        op,
+        number_of(a)?,
+        number_of(b)?,
+    )))
+}
+
+/// Whether every number in a value is an int or a ratio
+fn is_exact(value: &TokenValue) -> bool {
+    match value {
+        TokenValue::VecValue(v) => v.iter().all(is_exact),
+        other => matches!(
+            other,
+            TokenValue::IntValue(_) | TokenValue::BigIntValue(_) | TokenValue::RatioValue(_)
+        ),
+    }
+}
+
+fn is_finite(value: &TokenValue) -> bool {
+    match value {
+        TokenValue::VecValue(v) => v.iter().all(is_finite),
+        TokenValue::DecValue(v) => v.is_finite(),
+        _ => true,
+    }
+}
+
+fn has_zero(value: &TokenValue) -> bool {
+    match value {
+        TokenValue::VecValue(v) => v.iter().any(has_zero),
+        other => complex_of(other) == Some(Complex::new(0.0, 0.0)),
+    }
+}
+
+/// Whether `a ^ b` raises zero to a negative power anywhere, which divides by zero
+fn zero_to_negative(a: &TokenValue, b: &TokenValue) -> bool {
+    match (a, b) {
+        (TokenValue::VecValue(x), TokenValue::VecValue(y)) => {
+            x.iter().zip(y).any(|(m, n)| zero_to_negative(m, n))
+        }
+        (TokenValue::VecValue(x), n) => x.iter().any(|m| zero_to_negative(m, n)),
+        (m, TokenValue::VecValue(y)) => y.iter().any(|n| zero_to_negative(m, n)),
+        (m, n) => has_zero(m) && complex_of(n).is_some_and(|e| e.re < 0.0),
+    }
+}
+
+pub fn is_numeric(value: &Value) -> bool {
+    complex_of(&value.value).is_some() || value.components().is_some()
+}
+
+/// Apply an operator to each number, using a number with every component of a vector or matrix
+///
+/// Gives back `None` when two vectors or matrices are not the same size.
+fn elementwise(op: &TokenType, a: &TokenValue, b: &TokenValue) -> Option<TokenValue> {
+    let values = match (a, b) {
+        (TokenValue::VecValue(x), TokenValue::VecValue(y)) => {
+            if x.len() != y.len() {
+                return None;
+            }
+
+            x.iter()
+                .zip(y)
+                .map(|(m, n)| elementwise(op, m, n))
+                .collect::<Option<Vec<_>>>()
+        }
+        (TokenValue::VecValue(x), n) => x.iter().map(|m| elementwise(op, m, n)).collect(),
+        (m, TokenValue::VecValue(y)) => y.iter().map(|n| elementwise(op, m, n)).collect(),
+        (m, n) => return number_arithmetic(op, m, n),
+    };
+
+    values.map(TokenValue::VecValue)
+}
+
+/// Multiply every number in a value by a decimal, which is how values are converted between units
+pub fn scaled(value: &TokenValue, factor: f64) -> TokenValue {
+    elementwise(
+        &TokenType::Multiplication,
+        value,
+        &TokenValue::DecValue(factor),
+    )
+    .expect("A number works with a value of any size")
+}
+
+/// The unit of `a` and `b` after an operator, and `b` converted so it can be added to or
+/// subtracted from `a`
+pub fn combine_units(a: &Value, b: Value, op: &TokenType) -> Result<(Value, Unit), ComponentError> {
+    match op {
+        TokenType::Addition | TokenType::Subtraction | TokenType::Percent if a.unit == b.unit => {
+            Ok((b, a.unit.clone()))
+        }
+        TokenType::Addition | TokenType::Subtraction | TokenType::Percent => {
+            if a.unit.dimension() != b.unit.dimension() {
+                return Err(ComponentError::DimensionMismatch {
+                    values: vec![a.clone(), b],
+                    expected: None,
+                });
+            }
+
+            // Something like km and m are the same dimension, so b can be written in a's units
+            let value = scaled(&b.value, b.unit.scale() / a.unit.scale());
+            Ok((Value { value, ..b }, a.unit.clone()))
+        }
+        TokenType::Multiplication => match a.unit.mul(&b.unit) {
+            Some(unit) => Ok((b, unit)),
+            None => Err(too_big(vec![a.clone(), b])),
+        },
+        TokenType::Division | TokenType::IntDivision => match a.unit.div(&b.unit) {
+            Some(unit) => Ok((b, unit)),
+            None => Err(too_big(vec![a.clone(), b])),
+        },
+        _ if !b.unit.is_none() => Err(ComponentError::DimensionMismatch {
+            values: vec![b],
+            expected: Some("dimensionless".to_string()),
+        }),
+        _ if a.unit.is_none() => Ok((b, Unit::none())),
+        // Only whole powers of a unit make sense, like m^2
+        _ => match &b.value {
+            TokenValue::IntValue(e) if i32::try_from(*e).is_ok() => match a.unit.powi(*e as i32) {
+                Some(unit) => Ok((b, unit)),
+                None => Err(too_big(vec![a.clone()])),
+            },
+            _ => Err(ComponentError::WrongType {
+                values: vec![b],
+                expected: vec![TokenType::NumericIntLiteral],
+            }),
+        },
+    }
+}
+
+/// Apply one of the two argument operators `+ - * / // % ^` to two values
+///
+/// Vectors are worked on one component at a time, and a number with a vector or matrix is used
+/// with every component. Matrices can be added and subtracted, and `*` multiplies two matrices or
+/// a matrix and a vector. Values with units can only be added to and subtracted from values of
+/// the same dimension, and their units are multiplied and divided along with them. Dividing by
+/// zero is an error.
+pub fn arithmetic(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    // The result covers everything from the first value to the operator
+    let span = a.span.to(operation.span);
+    let op = &operation.token_type;
+
+    // `+` joins strings, and a number added to a string is joined the way it would be printed
+    let is_string = |v: &Value| v.value_type == TokenType::StringLiteral;
+    if *op == TokenType::Addition && (is_string(&a) || is_string(&b)) {
+        let text = format!("{}{}", a, b);
+        let wrong: Vec<Value> = [a, b]
+            .into_iter()
+            .filter(|v| !is_string(v) && !is_numeric(v))
+            .collect();
+
+        if !wrong.is_empty() {
+            let mut expected = vec![TokenType::StringLiteral];
+            expected.extend(operand_types());
+            return Err(ComponentError::WrongType {
+                values: wrong,
+                expected,
+            });
+        }
+
+        return Ok(Value::string(&text, span));
+    }
+
+    if !is_numeric(&a) || !is_numeric(&b) {
+        let values = [a, b].into_iter().filter(|v| !is_numeric(v)).collect();
+        return Err(ComponentError::WrongType {
+            values,
+            expected: operand_types(),
+        });
+    }
+
+    let (b, unit) = combine_units(&a, b, op)?;
+
+    let value = match (&a.value_type, &b.value_type, op) {
+        (TokenType::MatrixLiteral, TokenType::MatrixLiteral, TokenType::Multiplication)
+        | (TokenType::MatrixLiteral, TokenType::VectorLiteral, TokenType::Multiplication)
+        | (TokenType::VectorLiteral, TokenType::MatrixLiteral, TokenType::Multiplication) => {
+            matrix::product(&a, &b)
+        }
+        (TokenType::MatrixLiteral, TokenType::MatrixLiteral, _)
+            if !matches!(op, TokenType::Addition | TokenType::Subtraction) =>
+        {
+            return Err(ComponentError::WrongType {
+                values: vec![b],
+                expected: numeric_types(),
+            });
+        }
+        // Adding a vector to a matrix could mean rows or columns, so it has to be said with a
+        // matrix instead
+        (TokenType::MatrixLiteral, TokenType::VectorLiteral, _)
+        | (TokenType::VectorLiteral, TokenType::MatrixLiteral, _) => {
+            let vector = if a.value_type == TokenType::VectorLiteral {
+                a
+            } else {
+                b
+            };
+            return Err(ComponentError::WrongType {
+                values: vec![vector],
+                expected: numeric_types(),
+            });
+        }
+        // Complex numbers are only used on their own
+        (TokenType::ComplexLiteral, TokenType::VectorLiteral | TokenType::MatrixLiteral, _)
+        | (TokenType::VectorLiteral | TokenType::MatrixLiteral, TokenType::ComplexLiteral, _) => {
+            let complex = if a.value_type == TokenType::ComplexLiteral {
+                a
+            } else {
+                b
+            };
+            return Err(ComponentError::WrongType {
+                values: vec![complex],
+                expected: numeric_types(),
+            });
+        }
+        // `%` and `//` are only for real numbers
+        (TokenType::ComplexLiteral, _, TokenType::Percent | TokenType::IntDivision)
+        | (_, TokenType::ComplexLiteral, TokenType::Percent | TokenType::IntDivision) => {
+            let complex = if a.value_type == TokenType::ComplexLiteral {
+                a
+            } else {
+                b
+            };
+            return Err(ComponentError::WrongType {
+                values: vec![complex],
+                expected: numeric_types(),
+            });
+        }
+        // Dividing by zero is an error instead of giving `inf` or `NaN`
+        _ if matches!(
+            op,
+            TokenType::Division | TokenType::Percent | TokenType::IntDivision
+        ) && has_zero(&b.value) =>
+        {
+            return Err(ComponentError::DivisionByZero { value: Box::new(b) });
+        }
+        _ if *op == TokenType::Carrot && zero_to_negative(&a.value, &b.value) => {
+            return Err(ComponentError::DivisionByZero { value: Box::new(a) });
+        }
+        _ => elementwise(op, &a.value, &b.value),
+    };
+
+    match value {
+        // Exact numbers only become infinite when they are too big to keep
+        Some(value) if is_exact(&a.value) && is_exact(&b.value) && !is_finite(&value) => {
+            Err(ComponentError::NumberTooBig { values: vec![a, b] })
+        }
+        Some(value) => Ok(Value::from_number(value, span).with_unit(unit)),
+        None => Err(ComponentError::SizeMismatch {
+            values: vec![a, b],
+            expected: None,
+        }),
+    }
+}
+
+fn vector_types() -> Vec<TokenType> {
+    vec![TokenType::VectorLiteral]
+}
+
+/// Check that each value is a vector, giving back their components
+fn vectors<'a>(values: &[&'a Value]) -> Result<Vec<&'a Vec<TokenValue>>, ComponentError> {
+    let wrong: Vec<Value> = values
+        .iter()
+        .filter(|v| v.value_type != TokenType::VectorLiteral)
+        .map(|v| (*v).clone())
+        .collect();
+
+    if !wrong.is_empty() {
+        return Err(ComponentError::WrongType {
+            values: wrong,
+            expected: vector_types(),
+        });
+    }
+
+    Ok(values.iter().filter_map(|v| v.components()).collect())
+}
+
+pub fn sum_of_products(x: &[TokenValue], y: &[TokenValue]) -> TokenValue {
+    let products = x
+        .iter()
+        .zip(y)
+        .filter_map(|(m, n)| number_arithmetic(&TokenType::Multiplication, m, n));
+
+    products.fold(TokenValue::IntValue(0), |total, p| {
+        number_arithmetic(&TokenType::Addition, &total, &p).expect("Products are numbers")
+    })
+}
+
+/// The dot product of two vectors of the same size
+pub fn dot(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let (x, y) = match vectors(&[&a, &b])?[..] {
+        [x, y] => (x, y),
+        _ => unreachable!(),
+    };
+
+    if x.len() != y.len() {
+        return Err(ComponentError::SizeMismatch {
+            values: vec![a, b],
+            expected: None,
+        });
+    }
+
+    let unit = match a.unit.mul(&b.unit) {
+        Some(unit) => unit,
+        None => return Err(too_big(vec![a, b])),
+    };
+    Ok(Value::from_number(sum_of_products(x, y), span).with_unit(unit))
+}
+
+/// The cross product of two vectors of size 3
+pub fn cross(a: Value, b: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let (x, y) = match vectors(&[&a, &b])?[..] {
+        [x, y] => (x, y),
+        _ => unreachable!(),
+    };
+
+    if x.len() != 3 || y.len() != 3 {
+        let values = [a, b].into_iter().filter(|v| v.size() != 3).collect();
+        return Err(ComponentError::SizeMismatch {
+            values,
+            expected: Some("of size 3".to_string()),
+        });
+    }
+
+    // Each component is the 2x2 determinant of the other two components
+    let multiply = |m: &TokenValue, n: &TokenValue| {
+        number_arithmetic(&TokenType::Multiplication, m, n).expect("Components are numbers")
+    };
+    let component = |i: usize, j: usize| {
+        let first = multiply(&x[i], &y[j]);
+        let second = multiply(&x[j], &y[i]);
+        number_arithmetic(&TokenType::Subtraction, &first, &second).expect("Products are numbers")
+    };
+
+    let unit = match a.unit.mul(&b.unit) {
+        Some(unit) => unit,
+        None => return Err(too_big(vec![a, b])),
+    };
+    Ok(Value::vector(
+        vec![component(1, 2), component(2, 0), component(0, 1)],
+        span,
+    )
+    .with_unit(unit))
+}
+
+/// The length of a vector
+pub fn norm(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let x = vectors(&[&a])?[0];
+
+    let squares = number_of(&sum_of_products(x, x)).expect("Products are numbers");
+    Ok(Value::dec(squares.sqrt(), span).with_unit(a.unit.clone()))
+}
+
+/// The number of characters in a string, or the number of components in a vector
+pub fn len(a: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+
+    match (&a.value_type, &a.value) {
+        (TokenType::StringLiteral, TokenValue::StringValue(text)) => {
+            Ok(Value::int(text.chars().count() as i64, span))
+        }
+        (TokenType::VectorLiteral | TokenType::MatrixLiteral, _) => {
+            Ok(Value::int(a.size() as i64, span))
+        }
+        _ => Err(ComponentError::WrongType {
+            values: vec![a],
+            expected: vec![TokenType::StringLiteral, TokenType::VectorLiteral],
+        }),
+    }
+}
+
+/// The component of a vector, or the row of a matrix, at an index starting from 0
+pub fn get(a: Value, index: Value, operation: &Token) -> Result<Value, ComponentError> {
+    let span = a.span.to(operation.span);
+    let x = match a.components() {
+        Some(x) => x,
+        None => {
+            return Err(ComponentError::WrongType {
+                values: vec![a],
+                expected: vec![TokenType::VectorLiteral, TokenType::MatrixLiteral],
+            })
+        }
+    };
+
+    let i = match index.value {
+        TokenValue::IntValue(i) => i,
+        // Too big to be an index of anything
+        TokenValue::BigIntValue(_) => -1,
+        _ => {
+            return Err(ComponentError::WrongType {
+                values: vec![index],
+                expected: vec![TokenType::NumericIntLiteral],
+            })
+        }
+    };
+
+    match usize::try_from(i).ok().and_then(|i| x.get(i)) {
+        Some(v) => Ok(Value::from_number(v.clone(), span).with_unit(a.unit.clone())),
+        None => Err(ComponentError::IndexOutOfRange {
+            value: Box::new(a),
+            index: Box::new(index),
+        }),
+    }
+}